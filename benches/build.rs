@@ -1,9 +1,10 @@
 use std::path::Path;
 
+use clap::Parser;
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
 use tempfile::{tempdir, TempDir};
 
-use librojo::cli::BuildCommand;
+use librojo::cli::{BuildCommand, GlobalOptions};
 
 pub fn benchmark_small_place(c: &mut Criterion) {
     bench_build_place(c, "Small Place", "test-projects/benchmark_small_place")
@@ -20,7 +21,7 @@ fn bench_build_place(c: &mut Criterion, name: &str, path: &str) {
     group.bench_function("build", |b| {
         b.iter_batched(
             || place_setup(path),
-            |(_dir, options)| options.run().unwrap(),
+            |(_dir, options)| options.run(GlobalOptions::parse_from(["rojo"])).unwrap(),
             BatchSize::SmallInput,
         )
     });
@@ -38,6 +39,7 @@ fn place_setup<P: AsRef<Path>>(input_path: P) -> (TempDir, BuildCommand) {
         watch: false,
         plugin: None,
         output,
+        profile: None,
     };
 
     (dir, options)
@@ -21,7 +21,7 @@ return strict("Config", {
 	codename = "Epiphany",
 	version = realVersion,
 	expectedServerVersionString = ("%d.%d or newer"):format(realVersion[1], realVersion[2]),
-	protocolVersion = 4,
+	protocolVersion = 5,
 	defaultHost = "localhost",
 	defaultPort = "34872",
 })
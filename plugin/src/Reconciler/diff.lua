@@ -96,12 +96,39 @@ local function trueEquals(a, b): boolean
 	return false
 end
 
-local function shouldDeleteUnknownInstances(virtualInstance)
-	if virtualInstance.Metadata ~= nil then
-		return not virtualInstance.Metadata.ignoreUnknownInstances
-	else
+-- Tells whether an instance matches one of the patterns given by
+-- ignoreUnknownInstances. Patterns are either a name, like "Terrain", or a
+-- class, like "class:Camera", which also matches subclasses.
+local function matchesUnknownInstancePattern(instance, patterns)
+	for _, pattern in ipairs(patterns) do
+		local className = string.match(pattern, "^class:(.+)$")
+
+		if className ~= nil then
+			local ok, isA = pcall(instance.IsA, instance, className)
+
+			if ok and isA then
+				return true
+			end
+		elseif instance.Name == (string.match(pattern, "^name:(.+)$") or pattern) then
+			return true
+		end
+	end
+
+	return false
+end
+
+local function shouldDeleteUnknownInstance(virtualInstance, instance)
+	if virtualInstance.Metadata == nil then
 		return true
 	end
+
+	local ignoreUnknownInstances = virtualInstance.Metadata.ignoreUnknownInstances
+
+	if typeof(ignoreUnknownInstances) == "table" then
+		return not matchesUnknownInstancePattern(instance, ignoreUnknownInstances)
+	else
+		return not ignoreUnknownInstances
+	end
 end
 
 local function diff(instanceMap, virtualInstances, rootId)
@@ -213,7 +240,7 @@ local function diff(instanceMap, virtualInstances, rootId)
 				-- This is an existing instance not present in the virtual DOM.
 				-- We can mark it for deletion unless the user has asked us not
 				-- to delete unknown stuff.
-				if shouldDeleteUnknownInstances(virtualInstance) then
+				if shouldDeleteUnknownInstance(virtualInstance, childInstance) then
 					table.insert(patch.removed, childInstance)
 				end
 			else
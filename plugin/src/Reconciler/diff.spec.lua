@@ -257,6 +257,43 @@ return function()
 		assert(isEmpty(patch.removed))
 	end)
 
+	it("should only keep unknown children that match ignore patterns", function()
+		local knownInstances = InstanceMap.new()
+		local virtualInstances = {
+			ROOT = {
+				ClassName = "Folder",
+				Name = "Folder",
+				Properties = {},
+				Children = {},
+				Metadata = {
+					ignoreUnknownInstances = { "Terrain", "class:BasePart" },
+				},
+			},
+		}
+
+		local rootInstance = Instance.new("Folder")
+		knownInstances:insert("ROOT", rootInstance)
+
+		local namedChild = Instance.new("Folder")
+		namedChild.Name = "Terrain"
+		namedChild.Parent = rootInstance
+
+		local classChild = Instance.new("Part")
+		classChild.Parent = rootInstance
+
+		local unknownChild = Instance.new("Folder")
+		unknownChild.Parent = rootInstance
+
+		local ok, patch = diff(knownInstances, virtualInstances, "ROOT")
+
+		assert(ok, tostring(patch))
+
+		assert(isEmpty(patch.added))
+		assert(isEmpty(patch.updated))
+		expect(#patch.removed).to.equal(1)
+		expect(patch.removed[1]).to.equal(unknownChild)
+	end)
+
 	it("should generate a patch with an added child", function()
 		local knownInstances = InstanceMap.new()
 		local virtualInstances = {
@@ -8,7 +8,7 @@ local RbxId = t.string
 local ApiValue = t.keys(t.string)
 
 local ApiInstanceMetadata = t.interface({
-	ignoreUnknownInstances = t.optional(t.boolean),
+	ignoreUnknownInstances = t.optional(t.union(t.boolean, t.array(t.string))),
 })
 
 local ApiInstance = t.interface({
@@ -0,0 +1,162 @@
+//! Asset manifests map files kept in a project, like images, sounds, and
+//! meshes, to the IDs they were uploaded to Roblox with. `Content` properties
+//! and data files can then refer to those files as `@asset/path/to/file.png`
+//! instead of hard-coding their asset IDs.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use memofs::Vfs;
+use serde::{Deserialize, Serialize};
+
+/// The prefix that marks a string as a reference to an asset in the manifest.
+pub const ASSET_PREFIX: &str = "@asset/";
+
+/// An asset manifest, which is read from a lock file like `assets.lock.json`:
+///
+/// ```json
+/// {
+///     "assets": {
+///         "images/icon.png": 1818,
+///         "sounds/click.ogg": null
+///     }
+/// }
+/// ```
+///
+/// Paths are relative to the folder containing the manifest. Assets that
+/// haven't been uploaded yet are listed with an ID of `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct AssetManifest {
+    #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+    schema: Option<String>,
+
+    pub assets: BTreeMap<String, Option<u64>>,
+
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl AssetManifest {
+    pub fn load(vfs: &Vfs, path: &Path) -> anyhow::Result<Self> {
+        let contents = vfs
+            .read(path)
+            .with_context(|| format!("Could not read asset manifest {}", path.display()))?;
+
+        let mut manifest: Self = serde_json::from_slice(&contents)
+            .with_context(|| format!("Asset manifest is malformed: {}", path.display()))?;
+
+        manifest.path = path.to_path_buf();
+        Ok(manifest)
+    }
+
+    /// Returns the path of the given asset on disk.
+    pub fn asset_path(&self, asset: &str) -> PathBuf {
+        self.path.parent().unwrap().join(asset)
+    }
+
+    /// Returns the assets that are listed in the manifest without an ID.
+    pub fn missing_ids(&self) -> impl Iterator<Item = &str> {
+        self.assets
+            .iter()
+            .filter(|(_, id)| id.is_none())
+            .map(|(asset, _)| asset.as_str())
+    }
+
+    /// If `value` is an `@asset/` reference, returns the content URL of the
+    /// asset it refers to. Errors if the asset isn't in the manifest or
+    /// doesn't have an ID yet.
+    pub fn resolve(&self, value: &str) -> anyhow::Result<Option<String>> {
+        let asset = match value.strip_prefix(ASSET_PREFIX) {
+            Some(asset) => asset,
+            None => return Ok(None),
+        };
+
+        match self.assets.get(asset) {
+            Some(Some(id)) => Ok(Some(format!("rbxassetid://{}", id))),
+            Some(None) => bail!(
+                "Asset \"{}\" does not have an ID yet. Upload it, then add its ID to {}",
+                value,
+                self.path.display()
+            ),
+            None => bail!(
+                "Unknown asset \"{}\": it is not listed in the asset manifest {}",
+                value,
+                self.path.display()
+            ),
+        }
+    }
+}
+
+/// Resolves `value` using the given manifest, if there is one. Errors if the
+/// value is an `@asset/` reference and there's no manifest to resolve it with.
+pub fn resolve_asset(
+    manifest: Option<&AssetManifest>,
+    value: &str,
+) -> anyhow::Result<Option<String>> {
+    match manifest {
+        Some(manifest) => manifest.resolve(value),
+        None if value.starts_with(ASSET_PREFIX) => bail!(
+            "Asset \"{}\" cannot be used because the project does not have an assetManifest",
+            value
+        ),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn manifest() -> AssetManifest {
+        let mut manifest: AssetManifest = serde_json::from_str(
+            r#"{
+                "assets": {
+                    "images/icon.png": 1818,
+                    "sounds/click.ogg": null
+                }
+            }"#,
+        )
+        .unwrap();
+
+        manifest.path = PathBuf::from("/project/assets.lock.json");
+        manifest
+    }
+
+    #[test]
+    fn resolve_assets() {
+        let manifest = manifest();
+
+        assert_eq!(
+            manifest.resolve("@asset/images/icon.png").unwrap(),
+            Some("rbxassetid://1818".to_owned())
+        );
+        assert_eq!(manifest.resolve("rbxassetid://1234").unwrap(), None);
+
+        let missing_id = manifest.resolve("@asset/sounds/click.ogg").unwrap_err();
+        assert!(missing_id.to_string().contains("does not have an ID"));
+
+        let unknown = manifest.resolve("@asset/images/missing.png").unwrap_err();
+        assert!(unknown.to_string().contains("Unknown asset"));
+
+        assert!(resolve_asset(None, "@asset/images/icon.png").is_err());
+        assert_eq!(resolve_asset(None, "rbxassetid://1234").unwrap(), None);
+    }
+
+    #[test]
+    fn missing_ids() {
+        let manifest = manifest();
+
+        assert_eq!(
+            manifest.missing_ids().collect::<Vec<_>>(),
+            vec!["sounds/click.ogg"]
+        );
+        assert_eq!(
+            manifest.asset_path("sounds/click.ogg"),
+            PathBuf::from("/project/sounds/click.ogg")
+        );
+    }
+}
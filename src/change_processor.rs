@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     fs,
     sync::{Arc, Mutex},
 };
@@ -159,6 +160,8 @@ impl JobThreadContext {
                     }
                 }
 
+                log_tree_errors(&mut tree);
+
                 applied_patches
             }
             _ => {
@@ -253,7 +256,10 @@ impl JobThreadContext {
                 }
             }
 
-            apply_patch_set(&mut tree, patch_set)
+            let applied_patch = apply_patch_set(&mut tree, patch_set);
+            log_tree_errors(&mut tree);
+
+            applied_patch
         };
 
         if !applied_patch.is_empty() {
@@ -262,6 +268,14 @@ impl JobThreadContext {
     }
 }
 
+/// Problems with the tree don't stop a serve session once it's running, since
+/// they're usually fixed by the next change.
+fn log_tree_errors(tree: &mut RojoTree) {
+    for error in tree.take_errors() {
+        log::error!("{}", error);
+    }
+}
+
 fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<AppliedPatchSet> {
     let metadata = tree
         .get_metadata(id)
@@ -288,7 +302,7 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                 // path still exists. We can generate a snapshot starting at
                 // that path and use it as the source for our patch.
 
-                let snapshot = match snapshot_from_vfs(&metadata.context, vfs, path) {
+                let mut snapshot = match snapshot_from_vfs(&metadata.context, vfs, path) {
                     Ok(snapshot) => snapshot,
                     Err(err) => {
                         log::error!("Snapshot error: {:?}", err);
@@ -296,6 +310,13 @@ fn compute_and_apply_changes(tree: &mut RojoTree, vfs: &Vfs, id: Ref) -> Option<
                     }
                 };
 
+                // Whatever named this instance originally isn't being run
+                // again, so the name it gave has to be carried over.
+                if let (Some(snapshot), Some(name)) = (&mut snapshot, &metadata.name_override) {
+                    snapshot.name = Cow::Owned(name.clone());
+                    snapshot.metadata.name_override = Some(name.clone());
+                }
+
                 let patch_set = compute_patch_set(snapshot, tree, id);
                 apply_patch_set(tree, patch_set)
             }
@@ -0,0 +1,65 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use clap::Parser;
+use memofs::Vfs;
+
+use crate::{asset_manifest::AssetManifest, project::Project};
+
+use super::resolve_path;
+
+/// Lists the assets in the project's asset manifest that don't have an asset
+/// ID yet.
+#[derive(Debug, Parser)]
+pub struct AssetsCommand {
+    /// Path to the project to check. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+}
+
+impl AssetsCommand {
+    pub fn run(self) -> anyhow::Result<()> {
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let base_path = resolve_path(&self.project);
+        let project = Project::load_fuzzy(&vfs, &base_path)?
+            .context("A project file is required to run 'rojo assets'")?;
+
+        let manifest_path = project.asset_manifest.as_ref().with_context(|| {
+            format!(
+                "Project {} does not have an assetManifest",
+                project.file_location.display()
+            )
+        })?;
+
+        let manifest = AssetManifest::load(&vfs, &project.folder_location().join(manifest_path))?;
+
+        let stdout = io::stdout();
+        let mut output = stdout.lock();
+        let mut missing_count = 0;
+
+        for asset in manifest.missing_ids() {
+            if manifest.asset_path(asset).exists() {
+                writeln!(output, "{}", asset)?;
+            } else {
+                writeln!(output, "{} (file not found)", asset)?;
+            }
+
+            missing_count += 1;
+        }
+
+        writeln!(
+            output,
+            "{} of {} asset(s) in {} are missing an ID",
+            missing_count,
+            manifest.assets.len(),
+            manifest.path.display()
+        )?;
+
+        Ok(())
+    }
+}
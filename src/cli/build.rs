@@ -11,9 +11,9 @@ use memofs::Vfs;
 use roblox_install::RobloxStudio;
 use tokio::runtime::Runtime;
 
-use crate::serve_session::ServeSession;
+use crate::serve_session::{ServeSession, ServeSessionOptions};
 
-use super::resolve_path;
+use super::{resolve_path, GlobalOptions};
 
 const UNKNOWN_OUTPUT_KIND_ERR: &str = "Could not detect what kind of file to build. \
                                        Expected output file to end in .rbxl, .rbxlx, .rbxm, or .rbxmx.";
@@ -42,10 +42,15 @@ pub struct BuildCommand {
     /// Whether to automatically rebuild when any input files change.
     #[clap(long)]
     pub watch: bool,
+
+    /// The name of a build profile from the project to apply to scripts, like
+    /// stripping comments or inlining constants.
+    #[clap(long)]
+    pub profile: Option<String>,
 }
 
 impl BuildCommand {
-    pub fn run(self) -> anyhow::Result<()> {
+    pub fn run(self, global: GlobalOptions) -> anyhow::Result<()> {
         let (output_path, output_kind) = match (self.output, self.plugin) {
             (None, None) => {
                 BuildCommand::command()
@@ -81,7 +86,11 @@ impl BuildCommand {
         let vfs = Vfs::new_default();
         vfs.set_watch_enabled(self.watch);
 
-        let session = ServeSession::new(vfs, project_path)?;
+        let options = ServeSessionOptions {
+            build_profile: self.profile.clone(),
+            ..global.serve_session_options()
+        };
+        let session = ServeSession::with_options(vfs, project_path, &options)?;
         let mut cursor = session.message_queue().cursor();
 
         write_model(&session, &output_path, output_kind)?;
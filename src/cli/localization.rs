@@ -0,0 +1,95 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    mem::forget,
+    path::PathBuf,
+};
+
+use clap::Parser;
+use memofs::{IoResultExt, Vfs};
+
+use crate::{serve_session::ServeSession, snapshot_middleware::validate_localization_csv};
+
+use super::{resolve_path, GlobalOptions};
+
+/// Reports problems in the project's localization CSV files, like duplicate
+/// entries, unknown locales, and missing translations.
+#[derive(Debug, Parser)]
+pub struct LocalizationCommand {
+    /// Path to the project to check. Defaults to the current directory.
+    #[clap(default_value = "")]
+    pub project: PathBuf,
+}
+
+impl LocalizationCommand {
+    pub fn run(self, global: GlobalOptions) -> anyhow::Result<()> {
+        let project_path = resolve_path(&self.project);
+
+        log::trace!("Constructing in-memory filesystem");
+        let vfs = Vfs::new_default();
+        vfs.set_watch_enabled(false);
+
+        let session =
+            ServeSession::with_options(vfs, project_path, &global.serve_session_options())?;
+
+        let csv_paths = {
+            let tree = session.tree();
+            let mut csv_paths = BTreeSet::new();
+
+            for instance in tree.descendants(tree.get_root_id()) {
+                if instance.class_name() != "LocalizationTable" {
+                    continue;
+                }
+
+                // Directories with an init.csv list it alongside their other
+                // possible init files, so only paths that exist count.
+                for path in &instance.metadata().relevant_paths {
+                    let is_csv = path.extension().and_then(|ext| ext.to_str()) == Some("csv");
+
+                    if is_csv && session.vfs().metadata(path).with_not_found()?.is_some() {
+                        csv_paths.insert(path.clone());
+                    }
+                }
+            }
+
+            csv_paths
+        };
+
+        let stdout = io::stdout();
+        let mut output = stdout.lock();
+        let mut problem_count = 0;
+
+        for path in &csv_paths {
+            let contents = session.vfs().read(path)?;
+            let problems = match validate_localization_csv(&contents) {
+                Ok(report) => report.problems(),
+                Err(err) => vec![format!("Could not read CSV: {}", err)],
+            };
+
+            if problems.is_empty() {
+                continue;
+            }
+
+            writeln!(output, "{}", path.display())?;
+            for problem in &problems {
+                writeln!(output, "  {}", problem)?;
+            }
+            writeln!(output)?;
+
+            problem_count += problems.len();
+        }
+
+        writeln!(
+            output,
+            "Found {} problem(s) in {} localization table(s)",
+            problem_count,
+            csv_paths.len()
+        )?;
+
+        // Avoid dropping ServeSession: it's potentially VERY expensive to drop
+        // and we're about to exit anyways.
+        forget(session);
+
+        Ok(())
+    }
+}
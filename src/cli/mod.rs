@@ -1,23 +1,34 @@
 //! Defines Rojo's CLI through clap types.
 
+mod assets;
 mod build;
 mod doc;
 mod fmt_project;
 mod init;
+mod localization;
 mod plugin;
 mod serve;
 mod sourcemap;
 mod upload;
 
-use std::{borrow::Cow, env, path::Path, str::FromStr};
+use std::{
+    borrow::Cow,
+    env,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use clap::Parser;
 use thiserror::Error;
 
+use crate::{reflection, serve_session::ServeSessionOptions};
+
+pub use self::assets::AssetsCommand;
 pub use self::build::BuildCommand;
 pub use self::doc::DocCommand;
 pub use self::fmt_project::FmtProjectCommand;
 pub use self::init::{InitCommand, InitKind};
+pub use self::localization::LocalizationCommand;
 pub use self::plugin::{PluginCommand, PluginSubcommand};
 pub use self::serve::ServeCommand;
 pub use self::sourcemap::SourcemapCommand;
@@ -37,15 +48,22 @@ pub struct Options {
 
 impl Options {
     pub fn run(self) -> anyhow::Result<()> {
+        if let Some(database_path) = &self.global.reflection_database {
+            let contents = fs_err::read(database_path)?;
+            reflection::load_command_line_database(database_path, &contents)?;
+        }
+
         match self.subcommand {
             Subcommand::Init(subcommand) => subcommand.run(),
             Subcommand::Serve(subcommand) => subcommand.run(self.global),
-            Subcommand::Build(subcommand) => subcommand.run(),
-            Subcommand::Upload(subcommand) => subcommand.run(),
-            Subcommand::Sourcemap(subcommand) => subcommand.run(),
+            Subcommand::Build(subcommand) => subcommand.run(self.global),
+            Subcommand::Upload(subcommand) => subcommand.run(self.global),
+            Subcommand::Sourcemap(subcommand) => subcommand.run(self.global),
             Subcommand::FmtProject(subcommand) => subcommand.run(),
             Subcommand::Doc(subcommand) => subcommand.run(),
             Subcommand::Plugin(subcommand) => subcommand.run(),
+            Subcommand::Localization(subcommand) => subcommand.run(self.global),
+            Subcommand::Assets(subcommand) => subcommand.run(),
         }
     }
 }
@@ -59,6 +77,26 @@ pub struct GlobalOptions {
     /// Set color behavior. Valid values are auto, always, and never.
     #[clap(long("color"), global(true), default_value("auto"))]
     pub color: ColorChoice,
+
+    /// Path to a reflection database to use instead of the one bundled with
+    /// Rojo. This can be a MessagePack file or a JSON dump.
+    #[clap(long("reflection-database"), global(true))]
+    pub reflection_database: Option<PathBuf>,
+
+    /// Allows projects to run the commands given by sync rules that use the
+    /// command middleware. Only use this with projects you trust.
+    #[clap(long("allow-commands"), global(true))]
+    pub allow_commands: bool,
+}
+
+impl GlobalOptions {
+    /// The options for serve sessions started by this invocation.
+    pub fn serve_session_options(&self) -> ServeSessionOptions {
+        ServeSessionOptions {
+            allow_commands: self.allow_commands,
+            ..ServeSessionOptions::default()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -119,6 +157,8 @@ pub enum Subcommand {
     FmtProject(FmtProjectCommand),
     Doc(DocCommand),
     Plugin(PluginCommand),
+    Localization(LocalizationCommand),
+    Assets(AssetsCommand),
 }
 
 pub(super) fn resolve_path(path: &Path) -> Cow<'_, Path> {
@@ -39,7 +39,11 @@ impl ServeCommand {
 
         let vfs = Vfs::new_default();
 
-        let session = Arc::new(ServeSession::new(vfs, project_path)?);
+        let session = Arc::new(ServeSession::with_options(
+            vfs,
+            project_path,
+            &global.serve_session_options(),
+        )?);
 
         let ip = self
             .address
@@ -17,7 +17,7 @@ use crate::{
     snapshot::{AppliedPatchSet, InstanceWithMeta, RojoTree},
 };
 
-use super::resolve_path;
+use super::{resolve_path, GlobalOptions};
 
 const PATH_STRIP_FAILED_ERR: &str = "Failed to create relative paths for project file!";
 
@@ -60,14 +60,15 @@ pub struct SourcemapCommand {
 }
 
 impl SourcemapCommand {
-    pub fn run(self) -> anyhow::Result<()> {
+    pub fn run(self, global: GlobalOptions) -> anyhow::Result<()> {
         let project_path = resolve_path(&self.project);
 
         log::trace!("Constructing in-memory filesystem");
         let vfs = Vfs::new_default();
         vfs.set_watch_enabled(self.watch);
 
-        let session = ServeSession::new(vfs, project_path)?;
+        let session =
+            ServeSession::with_options(vfs, project_path, &global.serve_session_options())?;
         let mut cursor = session.message_queue().cursor();
 
         let filter = if self.include_non_scripts {
@@ -11,7 +11,7 @@ use reqwest::{
 
 use crate::{auth_cookie::get_auth_cookie, serve_session::ServeSession};
 
-use super::resolve_path;
+use super::{resolve_path, GlobalOptions};
 
 /// Builds the project and uploads it to Roblox.
 #[derive(Debug, Parser)]
@@ -38,12 +38,13 @@ pub struct UploadCommand {
 }
 
 impl UploadCommand {
-    pub fn run(self) -> Result<(), anyhow::Error> {
+    pub fn run(self, global: GlobalOptions) -> Result<(), anyhow::Error> {
         let project_path = resolve_path(&self.project);
 
         let vfs = Vfs::new_default();
 
-        let session = ServeSession::new(vfs, project_path)?;
+        let session =
+            ServeSession::with_options(vfs, project_path, &global.serve_session_options())?;
 
         let tree = session.tree();
         let inner_tree = tree.inner();
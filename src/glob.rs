@@ -22,6 +22,10 @@ impl Glob {
         Ok(Glob { inner, matcher })
     }
 
+    pub fn as_str(&self) -> &str {
+        self.inner.glob()
+    }
+
     pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
         self.matcher.is_match(path)
     }
@@ -7,14 +7,17 @@ pub mod cli;
 #[cfg(test)]
 mod tree_view;
 
+mod asset_manifest;
 mod auth_cookie;
 mod change_processor;
 mod glob;
 mod lua_ast;
+mod luau_transform;
 mod message_queue;
 mod multimap;
 mod path_serializer;
 mod project;
+mod reflection;
 mod resolution;
 mod rojo_ref;
 mod serve_session;
@@ -1,5 +1,6 @@
 //! Defines module for defining a small Lua AST for simple codegen. Rojo uses
-//! this module to convert JSON into generated Lua code.
+//! this module to convert JSON into generated Lua code, along with the Luau
+//! types that describe it.
 
 use std::{
     fmt::{self, Write},
@@ -37,6 +38,12 @@ where
 
 pub(crate) enum Statement {
     Return(Expression),
+
+    /// `export type Name = Type`
+    ExportType(String, Type),
+
+    /// `local name: Type = value`, where the type annotation is optional.
+    Local(String, Option<Type>, Expression),
 }
 
 impl FmtLua for Statement {
@@ -46,6 +53,21 @@ impl FmtLua for Statement {
                 write!(output, "return ")?;
                 literal.fmt_lua(output)
             }
+            Self::ExportType(name, ty) => {
+                write!(output, "export type {} = ", name)?;
+                ty.fmt_lua(output)
+            }
+            Self::Local(name, ty, value) => {
+                write!(output, "local {}", name)?;
+
+                if let Some(ty) = ty {
+                    write!(output, ": ")?;
+                    ty.fmt_lua(output)?;
+                }
+
+                write!(output, " = ")?;
+                value.fmt_lua(output)
+            }
         }
     }
 }
@@ -57,6 +79,30 @@ impl fmt::Display for Statement {
     }
 }
 
+/// A sequence of statements, separated by blank lines.
+pub(crate) struct Chunk(pub Vec<Statement>);
+
+impl FmtLua for Chunk {
+    fn fmt_lua(&self, output: &mut LuaStream<'_>) -> fmt::Result {
+        for (index, statement) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(output, "\n\n")?;
+            }
+
+            statement.fmt_lua(output)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Chunk {
+    fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
+        let mut stream = LuaStream::new(output);
+        FmtLua::fmt_lua(self, &mut stream)
+    }
+}
+
 pub(crate) enum Expression {
     Nil,
     Bool(bool),
@@ -67,12 +113,38 @@ pub(crate) enum Expression {
     /// Arrays are not technically distinct from other tables in Lua, but this
     /// representation is more convenient.
     Array(Vec<Expression>),
+
+    /// A reference to a local variable or global.
+    Name(String),
+
+    /// A call to the function with the given name, like `table.freeze`.
+    Call(String, Vec<Expression>),
 }
 
 impl Expression {
     pub fn table(entries: Vec<(Expression, Expression)>) -> Self {
         Self::Table(Table { entries })
     }
+
+    /// Wraps every table in this expression, including nested tables, in a
+    /// call to `table.freeze` so that none of them can be modified.
+    pub fn deep_freeze(self) -> Self {
+        let freeze = |value| Self::Call("table.freeze".to_owned(), vec![value]);
+
+        match self {
+            Self::Table(table) => freeze(Self::table(
+                table
+                    .entries
+                    .into_iter()
+                    .map(|(key, value)| (key, value.deep_freeze()))
+                    .collect(),
+            )),
+            Self::Array(values) => freeze(Self::Array(
+                values.into_iter().map(Self::deep_freeze).collect(),
+            )),
+            other => other,
+        }
+    }
 }
 
 impl FmtLua for Expression {
@@ -84,6 +156,20 @@ impl FmtLua for Expression {
             Self::String(inner) => inner.fmt_lua(output),
             Self::Table(inner) => inner.fmt_lua(output),
             Self::Array(inner) => inner.fmt_lua(output),
+            Self::Name(name) => write!(output, "{}", name),
+            Self::Call(name, arguments) => {
+                write!(output, "{}(", name)?;
+
+                for (index, argument) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(output, ", ")?;
+                    }
+
+                    argument.fmt_lua(output)?;
+                }
+
+                write!(output, ")")
+            }
         }
     }
 
@@ -95,10 +181,22 @@ impl FmtLua for Expression {
             Self::String(inner) => inner.fmt_table_key(output),
             Self::Table(inner) => inner.fmt_table_key(output),
             Self::Array(inner) => inner.fmt_table_key(output),
+            Self::Name(_) | Self::Call(..) => {
+                write!(output, "[")?;
+                self.fmt_lua(output)?;
+                write!(output, "]")
+            }
         }
     }
 }
 
+impl fmt::Display for Expression {
+    fn fmt(&self, output: &mut fmt::Formatter) -> fmt::Result {
+        let mut stream = LuaStream::new(output);
+        FmtLua::fmt_lua(self, &mut stream)
+    }
+}
+
 impl From<String> for Expression {
     fn from(value: String) -> Self {
         Self::String(value)
@@ -211,6 +309,208 @@ impl FmtLua for Table {
     }
 }
 
+/// A Luau type annotation, which can be inferred from the value of an
+/// expression.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Type {
+    Any,
+    Nil,
+    Boolean,
+    Number,
+    String,
+
+    /// `{ T }`
+    Array(Box<Type>),
+
+    /// A table with a known set of string keys, like `{ name: string }`.
+    Record(Vec<(String, Type)>),
+
+    /// `{ [K]: V }`, used for tables with keys that aren't all strings.
+    Map(Box<Type>, Box<Type>),
+
+    /// `T?`
+    Optional(Box<Type>),
+
+    /// `A | B`
+    Union(Vec<Type>),
+
+    /// A reference to a type by name, like one declared with `export type`.
+    Named(String),
+}
+
+impl Type {
+    /// Infers the narrowest type that describes the given expression.
+    pub fn infer(value: &Expression) -> Self {
+        match value {
+            Expression::Nil => Self::Nil,
+            Expression::Bool(_) => Self::Boolean,
+            Expression::Number(_) => Self::Number,
+            Expression::String(_) => Self::String,
+            Expression::Table(table) => {
+                let is_record = table
+                    .entries
+                    .iter()
+                    .all(|(key, _)| matches!(key, Expression::String(_)));
+
+                if is_record {
+                    Self::Record(
+                        table
+                            .entries
+                            .iter()
+                            .map(|(key, value)| match key {
+                                Expression::String(key) => (key.clone(), Self::infer(value)),
+                                _ => unreachable!(),
+                            })
+                            .collect(),
+                    )
+                } else {
+                    let key = Self::infer_all(table.entries.iter().map(|(key, _)| key));
+                    let value = Self::infer_all(table.entries.iter().map(|(_, value)| value));
+
+                    Self::Map(Box::new(key), Box::new(value))
+                }
+            }
+            Expression::Array(values) => Self::Array(Box::new(Self::infer_all(values))),
+            Expression::Name(_) | Expression::Call(..) => Self::Any,
+        }
+    }
+
+    /// Infers a type that describes all of the given expressions, or `any` if
+    /// there are none.
+    fn infer_all<'a, I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Expression>,
+    {
+        values
+            .into_iter()
+            .map(Self::infer)
+            .reduce(Self::union)
+            .unwrap_or(Self::Any)
+    }
+
+    /// Combines two types into one that describes values of either type.
+    /// Records are merged field by field, with fields that only one of them
+    /// has becoming optional.
+    pub fn union(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Self::Any, _) | (_, Self::Any) => Self::Any,
+            (Self::Nil, other) | (other, Self::Nil) => other.optional(),
+            (Self::Optional(inner), other) | (other, Self::Optional(inner)) => {
+                inner.union(other).optional()
+            }
+            (Self::Array(a), Self::Array(b)) => Self::Array(Box::new(a.union(*b))),
+            (Self::Map(a_key, a_value), Self::Map(b_key, b_value)) => Self::Map(
+                Box::new(a_key.union(*b_key)),
+                Box::new(a_value.union(*b_value)),
+            ),
+            (Self::Record(a), Self::Record(b)) => Self::Record(merge_records(a, b)),
+            (Self::Union(mut types), other) | (other, Self::Union(mut types)) => {
+                let others = match other {
+                    Self::Union(others) => others,
+                    other => vec![other],
+                };
+
+                for other in others {
+                    if !types.contains(&other) {
+                        types.push(other);
+                    }
+                }
+
+                Self::Union(types)
+            }
+            (a, b) => Self::Union(vec![a, b]),
+        }
+    }
+
+    fn optional(self) -> Self {
+        match self {
+            Self::Any | Self::Nil | Self::Optional(_) => self,
+            other => Self::Optional(Box::new(other)),
+        }
+    }
+}
+
+fn merge_records(a: Vec<(String, Type)>, mut b: Vec<(String, Type)>) -> Vec<(String, Type)> {
+    let mut fields = Vec::with_capacity(a.len().max(b.len()));
+
+    for (key, a_type) in a {
+        let field_type = match b.iter().position(|(b_key, _)| *b_key == key) {
+            Some(index) => a_type.union(b.remove(index).1),
+            None => a_type.optional(),
+        };
+
+        fields.push((key, field_type));
+    }
+
+    fields.extend(b.into_iter().map(|(key, ty)| (key, ty.optional())));
+    fields
+}
+
+impl FmtLua for Type {
+    fn fmt_lua(&self, output: &mut LuaStream<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(output, "any"),
+            Self::Nil => write!(output, "nil"),
+            Self::Boolean => write!(output, "boolean"),
+            Self::Number => write!(output, "number"),
+            Self::String => write!(output, "string"),
+            Self::Named(name) => write!(output, "{}", name),
+            Self::Array(inner) => {
+                write!(output, "{{ ")?;
+                inner.fmt_lua(output)?;
+                write!(output, " }}")
+            }
+            Self::Record(fields) => {
+                if fields.is_empty() {
+                    return write!(output, "{{}}");
+                }
+
+                writeln!(output, "{{")?;
+                output.indent();
+
+                for (key, ty) in fields {
+                    key.fmt_table_key(output)?;
+                    write!(output, ": ")?;
+                    ty.fmt_lua(output)?;
+                    writeln!(output, ",")?;
+                }
+
+                output.unindent();
+                write!(output, "}}")
+            }
+            Self::Map(key, value) => {
+                write!(output, "{{ [")?;
+                key.fmt_lua(output)?;
+                write!(output, "]: ")?;
+                value.fmt_lua(output)?;
+                write!(output, " }}")
+            }
+            Self::Optional(inner) => {
+                if let Self::Union(_) = **inner {
+                    write!(output, "(")?;
+                    inner.fmt_lua(output)?;
+                    write!(output, ")?")
+                } else {
+                    inner.fmt_lua(output)?;
+                    write!(output, "?")
+                }
+            }
+            Self::Union(types) => {
+                for (index, ty) in types.iter().enumerate() {
+                    if index > 0 {
+                        write!(output, " | ")?;
+                    }
+
+                    ty.fmt_lua(output)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
 fn is_valid_ident_char_start(value: char) -> bool {
     value.is_ascii_alphabetic() || value == '_'
 }
@@ -343,4 +643,71 @@ mod test {
 
         assert_eq!(displayed, "\"\\\"\\r\\n\\t\\\\\"");
     }
+
+    #[test]
+    fn infer_types() {
+        let value = Expression::table(vec![
+            ("name".into(), "Sword".into()),
+            ("1st".into(), Expression::Bool(true)),
+            (
+                "levels".into(),
+                Expression::Array(vec![Expression::Number(1.0), Expression::Nil]),
+            ),
+            (
+                "drops".into(),
+                Expression::Array(vec![
+                    Expression::table(vec![("id".into(), Expression::Number(1.0))]),
+                    Expression::table(vec![
+                        ("id".into(), Expression::Number(2.0)),
+                        ("rare".into(), Expression::Bool(true)),
+                    ]),
+                ]),
+            ),
+            (
+                "mixed".into(),
+                Expression::Array(vec![Expression::Number(1.0), "two".into(), Expression::Nil]),
+            ),
+            (
+                "lookup".into(),
+                Expression::table(vec![(Expression::Number(1.0), "one".into())]),
+            ),
+            ("empty".into(), Expression::Array(Vec::new())),
+        ]);
+
+        let displayed = format!("{}", DisplayLua(Type::infer(&value)));
+
+        assert_eq!(
+            displayed,
+            "{\n\
+             \tname: string,\n\
+             \t[\"1st\"]: boolean,\n\
+             \tlevels: { number? },\n\
+             \tdrops: { {\n\
+             \t\tid: number,\n\
+             \t\trare: boolean?,\n\
+             \t} },\n\
+             \tmixed: { (number | string)? },\n\
+             \tlookup: { [number]: string },\n\
+             \tempty: { any },\n\
+             }"
+        );
+    }
+
+    #[test]
+    fn deep_freeze() {
+        let value = Expression::table(vec![(
+            "list".into(),
+            Expression::Array(vec![Expression::Number(1.0)]),
+        )])
+        .deep_freeze();
+
+        let displayed = format!("{}", Statement::Return(value));
+
+        assert_eq!(
+            displayed,
+            "return table.freeze({\n\
+             \tlist = table.freeze({1}),\n\
+             })"
+        );
+    }
 }
@@ -0,0 +1,1234 @@
+//! Transforms that are applied to the source of scripts when building with a
+//! build profile, like stripping comments and inlining constants.
+//!
+//! Scripts declare constants with a `--!rojo-define` comment at the end of a
+//! `local` statement. The value written in the script is used while serving,
+//! and a build profile can give it a different value:
+//!
+//! ```lua
+//! local DEV = true --!rojo-define
+//!
+//! if DEV then
+//!     print("Running a development build")
+//! end
+//! ```
+//!
+//! When building, the declaration is removed, every use of the constant is
+//! replaced with its value, and `if` statements whose condition is a constant
+//! only keep the branch that would run.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::Range,
+    sync::Mutex,
+};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::lua_ast::Expression;
+
+const DEFINE_DIRECTIVE: &str = "--!rojo-define";
+
+/// A set of transforms to apply to scripts, which a project defines as one of
+/// its `buildProfiles`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct LuauTransforms {
+    /// Removes comments, except for directives like `--!strict` at the top of
+    /// a script.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_comments: bool,
+
+    /// Removes all whitespace that isn't needed. This strips comments too.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub minify: bool,
+
+    /// Values for constants declared with `--!rojo-define`, which take the
+    /// place of the values written in scripts.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defines: BTreeMap<String, DefineValue>,
+}
+
+/// The value of a constant in a build profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DefineValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+/// The names of every constant that scripts have declared so far, used to
+/// warn about build profiles that define constants no script declares.
+///
+/// Each serve session collects its own names, which are shared through the
+/// `InstanceContext` of every instance in it.
+#[derive(Default)]
+pub struct DeclaredConstants {
+    names: Mutex<BTreeSet<String>>,
+}
+
+impl fmt::Debug for DeclaredConstants {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeclaredConstants").finish_non_exhaustive()
+    }
+}
+
+/// The names never change what gets snapshotted, so they're ignored when
+/// contexts are compared.
+impl PartialEq for DeclaredConstants {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl DefineValue {
+    fn to_lua(&self) -> String {
+        let expression = match self {
+            Self::Bool(value) => Expression::Bool(*value),
+            Self::Number(value) => Expression::Number(*value),
+            Self::String(value) => Expression::String(value.clone()),
+        };
+
+        expression.to_string()
+    }
+}
+
+impl LuauTransforms {
+    /// Applies these transforms to the given source. The names of the
+    /// constants it declares are added to `declared`.
+    pub fn apply(&self, source: &str, declared: &DeclaredConstants) -> anyhow::Result<String> {
+        let mut tokens = tokenize(source)?;
+
+        inline_constants(&mut tokens, &self.defines, declared)?;
+        remove_constant_branches(&mut tokens);
+
+        if self.strip_comments || self.minify {
+            strip_comments(&mut tokens);
+        }
+
+        if self.minify {
+            Ok(minify(&tokens))
+        } else {
+            Ok(tokens.iter().map(|token| token.text.as_ref()).collect())
+        }
+    }
+
+    /// Returns the names in `defines` that aren't in `declared`, which are
+    /// most likely typos.
+    pub fn undeclared_defines(&self, declared: &DeclaredConstants) -> Vec<&str> {
+        let declared = declared.names.lock().unwrap();
+
+        self.defines
+            .keys()
+            .filter(|name| !declared.contains(name.as_str()))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Whitespace,
+    Comment,
+    Name,
+    Number,
+    String,
+    Symbol,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: Cow<'a, str>,
+}
+
+impl<'a> Token<'a> {
+    fn name(text: &'static str) -> Self {
+        Self {
+            kind: TokenKind::Name,
+            text: Cow::Borrowed(text),
+        }
+    }
+
+    fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+
+    /// Tells whether this token is the given keyword or symbol. Strings and
+    /// numbers never match, even if their text does.
+    fn is(&self, text: &str) -> bool {
+        matches!(self.kind, TokenKind::Name | TokenKind::Symbol) && self.text == text
+    }
+}
+
+const SYMBOLS: &[&str] = &[
+    "...", "..=", "//=", "..", "==", "~=", "<=", ">=", "//", "::", "->", "+=", "-=", "*=", "/=",
+    "%=", "^=",
+];
+
+/// Splits Luau source into tokens, keeping whitespace and comments so that
+/// the source can be put back together exactly.
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    while !rest.is_empty() {
+        let (kind, len) = next_token(source, rest)?;
+
+        tokens.push(Token {
+            kind,
+            text: Cow::Borrowed(&rest[..len]),
+        });
+        rest = &rest[len..];
+    }
+
+    Ok(tokens)
+}
+
+/// Finds the kind and length of the token at the start of `rest`, which is a
+/// suffix of `source`.
+fn next_token(source: &str, rest: &str) -> anyhow::Result<(TokenKind, usize)> {
+    let first = match rest.chars().next() {
+        Some(first) => first,
+        None => bail!("Unexpected end of script"),
+    };
+
+    let token = if first.is_whitespace() {
+        let len = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+
+        (TokenKind::Whitespace, len)
+    } else if let Some(comment) = rest.strip_prefix("--") {
+        let len = if long_bracket_level(comment).is_some() {
+            match long_bracket_len(comment) {
+                Some(len) => 2 + len,
+                None => bail!("Unfinished long comment on line {}", line_of(source, rest)),
+            }
+        } else {
+            rest.find('\n').unwrap_or(rest.len())
+        };
+
+        (TokenKind::Comment, len)
+    } else if first.is_ascii_alphabetic() || first == '_' {
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+
+        (TokenKind::Name, len)
+    } else if first.is_ascii_digit()
+        || (first == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+    {
+        (TokenKind::Number, number_len(rest))
+    } else if first == '"' || first == '\'' {
+        match quoted_string_len(rest, first) {
+            Some(len) => (TokenKind::String, len),
+            None => bail!("Unfinished string on line {}", line_of(source, rest)),
+        }
+    } else if first == '`' {
+        match scan_interpolated_string(source, rest)? {
+            Some((len, _)) => (TokenKind::String, len),
+            None => bail!("Unfinished string on line {}", line_of(source, rest)),
+        }
+    } else if long_bracket_level(rest).is_some() {
+        match long_bracket_len(rest) {
+            Some(len) => (TokenKind::String, len),
+            None => bail!("Unfinished long string on line {}", line_of(source, rest)),
+        }
+    } else {
+        let len = SYMBOLS
+            .iter()
+            .find(|symbol| rest.starts_with(*symbol))
+            .map(|symbol| symbol.len())
+            .unwrap_or(first.len_utf8());
+
+        (TokenKind::Symbol, len)
+    };
+
+    Ok(token)
+}
+
+/// Returns the line number that `rest`, a suffix of `source`, starts on.
+fn line_of(source: &str, rest: &str) -> usize {
+    let offset = source.len() - rest.len();
+    source[..offset].matches('\n').count() + 1
+}
+
+fn number_len(input: &str) -> usize {
+    let is_hex = input.starts_with("0x") || input.starts_with("0X");
+    let mut previous = '\0';
+
+    for (index, c) in input.char_indices() {
+        let exponent_sign =
+            (c == '+' || c == '-') && !is_hex && (previous == 'e' || previous == 'E');
+
+        if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || exponent_sign) {
+            return index;
+        }
+
+        previous = c;
+    }
+
+    input.len()
+}
+
+fn quoted_string_len(input: &str, quote: char) -> Option<usize> {
+    let mut chars = input.char_indices().skip(1);
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return None,
+            _ if c == quote => return Some(index + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Interpolated strings can contain expressions between braces, which can
+/// contain strings of their own, so those are tokenized to find their end.
+/// Returns the length of the string and the range of each expression in it.
+fn scan_interpolated_string(
+    source: &str,
+    input: &str,
+) -> anyhow::Result<Option<(usize, Vec<Range<usize>>)>> {
+    let mut index = 1;
+    let mut expressions = Vec::new();
+
+    while let Some(c) = input[index..].chars().next() {
+        match c {
+            '\\' => {
+                index += 1;
+                index += input[index..].chars().next().map_or(0, char::len_utf8);
+            }
+            '`' => return Ok(Some((index + 1, expressions))),
+            '{' => {
+                index += 1;
+                let start = index;
+                let mut depth = 1;
+
+                loop {
+                    if index == input.len() {
+                        return Ok(None);
+                    }
+
+                    let (kind, len) = next_token(source, &input[index..])?;
+
+                    if kind == TokenKind::Symbol {
+                        match &input[index..index + len] {
+                            "{" => depth += 1,
+                            "}" => depth -= 1,
+                            _ => {}
+                        }
+                    }
+
+                    if depth == 0 {
+                        expressions.push(start..index);
+                        index += len;
+                        break;
+                    }
+
+                    index += len;
+                }
+            }
+            _ => index += c.len_utf8(),
+        }
+    }
+
+    Ok(None)
+}
+
+/// If `input` starts with an opening long bracket like `[==[`, returns its
+/// level, which is the number of equals signs.
+fn long_bracket_level(input: &str) -> Option<usize> {
+    let rest = input.strip_prefix('[')?;
+    let level = rest.find(|c| c != '=').unwrap_or(rest.len());
+
+    if rest[level..].starts_with('[') {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+/// Returns the length of the long string or comment body at the start of
+/// `input`, including its brackets.
+fn long_bracket_len(input: &str) -> Option<usize> {
+    let level = long_bracket_level(input)?;
+    let close = format!("]{}]", "=".repeat(level));
+    let start = level + 2;
+
+    input[start..]
+        .find(&close)
+        .map(|index| start + index + close.len())
+}
+
+/// Returns the indices of the tokens that aren't whitespace or comments.
+fn significant(tokens: &[Token]) -> Vec<usize> {
+    (0..tokens.len())
+        .filter(|&index| !tokens[index].is_trivia())
+        .collect()
+}
+
+/// Finds constants declared with `--!rojo-define`, removes their
+/// declarations, and replaces every use of them with their value.
+fn inline_constants(
+    tokens: &mut Vec<Token>,
+    defines: &BTreeMap<String, DefineValue>,
+    declared: &DeclaredConstants,
+) -> anyhow::Result<()> {
+    let mut constants = BTreeMap::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match parse_define(tokens, index) {
+            Some((name, value, end)) => {
+                if constants.contains_key(&name) {
+                    bail!(
+                        "{} is declared with {} more than once, the second time on line {}",
+                        name,
+                        DEFINE_DIRECTIVE,
+                        line_of_token(tokens, index)
+                    );
+                }
+
+                let value = match defines.get(&name) {
+                    Some(value) => value.to_lua(),
+                    None => value,
+                };
+
+                constants.insert(name, constant_text(value));
+                tokens.drain(index..end);
+            }
+            None => index += 1,
+        }
+    }
+
+    // Any directive that's left wasn't part of a declaration that could be
+    // parsed, and silently leaving it alone would build with the wrong value.
+    if let Some(index) = tokens.iter().position(|token| {
+        token.kind == TokenKind::Comment && token.text.starts_with(DEFINE_DIRECTIVE)
+    }) {
+        bail!(
+            "The {} comment on line {} must end a declaration like `local NAME = value` \
+             or `local NAME: type = value`, where the value is a literal and the type is a single name",
+            DEFINE_DIRECTIVE,
+            line_of_token(tokens, index)
+        );
+    }
+
+    declared
+        .names
+        .lock()
+        .unwrap()
+        .extend(constants.keys().cloned());
+
+    if constants.is_empty() {
+        return Ok(());
+    }
+
+    // Constants are replaced by name without tracking scopes, so a local,
+    // parameter, or loop variable with the same name would have its uses
+    // replaced too.
+    if let Some(index) = find_rebinding(tokens, &constants) {
+        bail!(
+            "{} is declared with {}, so it can't be declared again on line {}",
+            tokens[index].text,
+            DEFINE_DIRECTIVE,
+            line_of_token(tokens, index)
+        );
+    }
+
+    replace_constants(tokens, &constants);
+    Ok(())
+}
+
+/// Returns the line number that `tokens[index]` starts on.
+fn line_of_token(tokens: &[Token], index: usize) -> usize {
+    tokens[..index]
+        .iter()
+        .map(|token| token.text.matches('\n').count())
+        .sum::<usize>()
+        + 1
+}
+
+/// Finds a local, function parameter, or loop variable that has the same name
+/// as one of the given constants, returning its index.
+fn find_rebinding(tokens: &[Token], constants: &BTreeMap<String, String>) -> Option<usize> {
+    let sig = significant(tokens);
+
+    sig.iter()
+        .enumerate()
+        .flat_map(|(position, &index)| {
+            let token = &tokens[index];
+
+            if token.is("local") || token.is("for") {
+                bound_names(tokens, &sig, position + 1)
+            } else if token.is("function") {
+                parameter_names(tokens, &sig, position + 1)
+            } else {
+                Vec::new()
+            }
+        })
+        .map(|position| sig[position])
+        .find(|&index| {
+            tokens[index].kind == TokenKind::Name
+                && constants.contains_key(tokens[index].text.as_ref())
+        })
+}
+
+/// Returns the positions of the names declared by a `local` or `for`
+/// statement whose names start at `start`, skipping any type annotations.
+fn bound_names(tokens: &[Token], sig: &[usize], start: usize) -> Vec<usize> {
+    let token = |position: usize| sig.get(position).map(|&index| &tokens[index]);
+
+    // The function's name; its parameters are found from the `function`.
+    if token(start).is_some_and(|token| token.is("function")) {
+        return token(start + 1).map(|_| start + 1).into_iter().collect();
+    }
+
+    let mut names = Vec::new();
+    let mut position = start;
+
+    'names: while token(position).is_some_and(|token| token.kind == TokenKind::Name) {
+        names.push(position);
+        position += 1;
+
+        // Type annotations can contain commas inside brackets, and they end
+        // at a comma, an `=` or `in`, or a name that can't continue the type.
+        let mut depth = 0usize;
+
+        while let Some(current) = token(position) {
+            match current.text.as_ref() {
+                "(" | "{" | "[" | "<" => depth += 1,
+                ")" | "}" | "]" | ">" if depth > 0 => depth -= 1,
+                "," if depth == 0 => {
+                    position += 1;
+                    continue 'names;
+                }
+                _ if depth > 0 => {}
+                "=" | "in" | ";" | ")" | "}" | "]" | ">" => break 'names,
+                _ if current.kind == TokenKind::Name => {
+                    let continues_type = token(position - 1).is_some_and(|previous| {
+                        matches!(previous.text.as_ref(), ":" | "|" | "&" | "." | "->")
+                    });
+
+                    if !continues_type {
+                        break 'names;
+                    }
+                }
+                _ => {}
+            }
+
+            position += 1;
+        }
+    }
+
+    names
+}
+
+/// Returns the positions of the parameters of a function whose `function`
+/// keyword is just before `start`.
+fn parameter_names(tokens: &[Token], sig: &[usize], start: usize) -> Vec<usize> {
+    let mut position = match (start..sig.len()).find(|&p| tokens[sig[p]].is("(")) {
+        Some(open) => open + 1,
+        None => return Vec::new(),
+    };
+
+    let mut names = Vec::new();
+    let mut depth = 1usize;
+
+    while let Some(&index) = sig.get(position) {
+        let token = &tokens[index];
+
+        match token.text.as_ref() {
+            "(" | "{" | "[" | "<" if token.kind == TokenKind::Symbol => depth += 1,
+            ")" | "}" | "]" | ">" if token.kind == TokenKind::Symbol => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ if token.kind == TokenKind::Name && depth == 1 => {
+                let previous = &tokens[sig[position - 1]];
+                if previous.is("(") || previous.is(",") {
+                    names.push(position);
+                }
+            }
+            _ => {}
+        }
+
+        position += 1;
+    }
+
+    names
+}
+
+/// Replaces every use of the given constants with their values, including
+/// uses inside the expressions of interpolated strings.
+fn replace_constants(tokens: &mut [Token], constants: &BTreeMap<String, String>) {
+    let sig = significant(tokens);
+
+    for (position, &index) in sig.iter().enumerate() {
+        let token = &tokens[index];
+
+        if token.kind == TokenKind::String && token.text.starts_with('`') {
+            if let Some(text) = replace_in_interpolated_string(&token.text, constants) {
+                tokens[index].text = Cow::Owned(text);
+            }
+            continue;
+        }
+
+        let value = match constants.get(token.text.as_ref()) {
+            Some(value) if token.kind == TokenKind::Name => value,
+            _ => continue,
+        };
+
+        // Fields, methods, and assignments that happen to use the same name
+        // aren't uses of the constant.
+        let previous = position.checked_sub(1).map(|p| &tokens[sig[p]]);
+        let next = sig.get(position + 1).map(|&n| &tokens[n]);
+
+        let is_use = !previous
+            .is_some_and(|token| token.is(".") || token.is(":") || token.is("function"))
+            && !next.is_some_and(|token| token.is("="));
+
+        if is_use {
+            let value = value.clone();
+            tokens[index].kind = token_kind_of(&value);
+            tokens[index].text = Cow::Owned(value);
+        }
+    }
+}
+
+fn replace_in_interpolated_string(
+    text: &str,
+    constants: &BTreeMap<String, String>,
+) -> Option<String> {
+    let (_, expressions) = scan_interpolated_string(text, text).ok()??;
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for range in expressions {
+        let mut tokens = tokenize(&text[range.clone()]).ok()?;
+        replace_constants(&mut tokens, constants);
+
+        output.push_str(&text[last..range.start]);
+        output.extend(tokens.iter().map(|token| token.text.as_ref()));
+        last = range.end;
+    }
+
+    output.push_str(&text[last..]);
+    Some(output)
+}
+
+/// Values that aren't a single token, like `-1` or `math.huge`, are wrapped
+/// in parentheses so that they stay one expression wherever they're used.
+fn constant_text(value: String) -> String {
+    match tokenize(&value) {
+        Ok(tokens) if tokens.len() == 1 => value,
+        _ => format!("({})", value),
+    }
+}
+
+fn token_kind_of(value: &str) -> TokenKind {
+    match value.chars().next() {
+        Some('"' | '\'' | '[') => TokenKind::String,
+        // Parenthesized values are always numbers, since they come from
+        // negative or special numbers.
+        Some(c) if c.is_ascii_digit() || c == '.' || c == '(' => TokenKind::Number,
+        _ => TokenKind::Name,
+    }
+}
+
+/// Matches a declaration like `local NAME: type = value --!rojo-define`
+/// starting at `tokens[start]`, returning the name, the value, and the index
+/// just past the directive.
+fn parse_define(tokens: &[Token], start: usize) -> Option<(String, String, usize)> {
+    if !tokens[start].is("local") {
+        return None;
+    }
+
+    // Only whitespace that stays on the same line may separate the parts of
+    // the declaration.
+    let mut index = start + 1;
+    let next = |index: &mut usize| -> Option<usize> {
+        while tokens.get(*index)?.kind == TokenKind::Whitespace {
+            if tokens[*index].text.contains('\n') {
+                return None;
+            }
+            *index += 1;
+        }
+
+        *index += 1;
+        Some(*index - 1)
+    };
+
+    let name = next(&mut index)?;
+    if tokens[name].kind != TokenKind::Name {
+        return None;
+    }
+
+    let mut equals = next(&mut index)?;
+    if tokens[equals].is(":") {
+        let annotation = next(&mut index)?;
+        if tokens[annotation].kind != TokenKind::Name {
+            return None;
+        }
+        equals = next(&mut index)?;
+    }
+
+    if !tokens[equals].is("=") {
+        return None;
+    }
+
+    let mut value_token = next(&mut index)?;
+    let mut value = String::new();
+
+    if tokens[value_token].is("-") {
+        value.push('-');
+        value_token = next(&mut index)?;
+        if tokens[value_token].kind != TokenKind::Number {
+            return None;
+        }
+    }
+
+    let token = &tokens[value_token];
+    let is_literal = match token.kind {
+        TokenKind::Number | TokenKind::String => true,
+        TokenKind::Name => token.is("true") || token.is("false") || token.is("nil"),
+        _ => false,
+    };
+
+    if !is_literal || token.text.starts_with('`') {
+        return None;
+    }
+    value.push_str(&token.text);
+
+    let comment = next(&mut index)?;
+    if tokens[comment].kind != TokenKind::Comment || tokens[comment].text.trim() != DEFINE_DIRECTIVE
+    {
+        return None;
+    }
+
+    Some((tokens[name].text.to_string(), value, comment + 1))
+}
+
+/// The parts of an `if` statement, as indices into the list of significant
+/// tokens.
+#[derive(Debug)]
+struct IfStatement {
+    start: usize,
+    /// The `elseif` and `else` keywords of the statement.
+    branches: Vec<usize>,
+    end: usize,
+}
+
+/// Finds every `if` statement in the given tokens. Luau's `if` expressions
+/// look similar but don't have an `end`, so they're told apart by what comes
+/// before them.
+fn find_if_statements(tokens: &[Token], sig: &[usize]) -> Vec<IfStatement> {
+    enum Frame {
+        Block,
+        If(IfStatement),
+        IfExpression,
+    }
+
+    let mut stack = Vec::new();
+    let mut statements = Vec::new();
+    let mut after_expression_else = false;
+
+    for (position, &index) in sig.iter().enumerate() {
+        let token = &tokens[index];
+        let previous_was_expression_else = after_expression_else;
+        after_expression_else = false;
+
+        if token.kind != TokenKind::Name {
+            continue;
+        }
+
+        match token.text.as_ref() {
+            "if" => {
+                let previous = position.checked_sub(1).map(|p| &tokens[sig[p]]);
+                let is_expression = match previous {
+                    Some(previous) if previous.is("else") => previous_was_expression_else,
+                    Some(previous) => expects_expression(previous),
+                    None => false,
+                };
+
+                if is_expression {
+                    stack.push(Frame::IfExpression);
+                } else {
+                    stack.push(Frame::If(IfStatement {
+                        start: position,
+                        branches: Vec::new(),
+                        end: position,
+                    }));
+                }
+            }
+            "function" | "do" | "repeat" => stack.push(Frame::Block),
+            "end" | "until" => {
+                while let Some(frame) = stack.pop() {
+                    match frame {
+                        Frame::IfExpression => continue,
+                        Frame::If(mut statement) => {
+                            statement.end = position;
+                            statements.push(statement);
+                        }
+                        Frame::Block => {}
+                    }
+                    break;
+                }
+            }
+            "elseif" => {
+                if let Some(Frame::If(statement)) = stack.last_mut() {
+                    statement.branches.push(position);
+                }
+            }
+            "else" => match stack.last_mut() {
+                Some(Frame::IfExpression) => {
+                    stack.pop();
+                    after_expression_else = true;
+                }
+                Some(Frame::If(statement)) => statement.branches.push(position),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    statements.sort_by_key(|statement| statement.start);
+    statements
+}
+
+/// Tells whether an expression is expected after the given token, which
+/// means an `if` after it must be an `if` expression.
+fn expects_expression(token: &Token) -> bool {
+    match token.kind {
+        TokenKind::Name => matches!(
+            token.text.as_ref(),
+            "return" | "and" | "or" | "not" | "in" | "if" | "elseif" | "while" | "until"
+        ),
+        TokenKind::Symbol => !matches!(token.text.as_ref(), ")" | "]" | "}" | ";" | "..."),
+        _ => false,
+    }
+}
+
+/// Returns whether the condition of an `if` statement is always truthy or
+/// always falsy, if it is.
+fn constant_condition(condition: &[&Token]) -> Option<bool> {
+    let truthiness = |token: &Token| match token.kind {
+        TokenKind::Number | TokenKind::String => Some(true),
+        TokenKind::Name if token.is("true") => Some(true),
+        TokenKind::Name if token.is("false") || token.is("nil") => Some(false),
+        _ => None,
+    };
+
+    match condition {
+        [value] => truthiness(value),
+        [not, value] if not.is("not") => truthiness(value).map(|truthy| !truthy),
+        _ => None,
+    }
+}
+
+/// Replaces `if` statements that have a constant condition with the branch
+/// that would run.
+fn remove_constant_branches(tokens: &mut Vec<Token>) {
+    loop {
+        let sig = significant(tokens);
+        let statements = find_if_statements(tokens, &sig);
+
+        let found = statements.iter().find_map(|statement| {
+            let then = (statement.start + 1..statement.end).find(|&p| tokens[sig[p]].is("then"))?;
+            let condition: Vec<_> = (statement.start + 1..then)
+                .map(|p| &tokens[sig[p]])
+                .collect();
+
+            constant_condition(&condition).map(|truthy| (statement, then, truthy))
+        });
+
+        let (statement, then, truthy) = match found {
+            Some(found) => found,
+            None => return,
+        };
+
+        let start = sig[statement.start];
+        let end = sig[statement.end];
+
+        match (truthy, statement.branches.first()) {
+            // The first branch always runs, so the rest are removed.
+            (true, branch) => {
+                if let Some(&branch) = branch {
+                    tokens.drain(sig[branch]..end);
+                }
+                tokens.splice(start..=sig[then], [Token::name("do")]);
+            }
+            (false, None) => {
+                tokens.drain(start..=end);
+            }
+            (false, Some(&branch)) => {
+                let replacement = if tokens[sig[branch]].is("elseif") {
+                    Token::name("if")
+                } else {
+                    Token::name("do")
+                };
+
+                tokens.splice(start..=sig[branch], [replacement]);
+            }
+        }
+    }
+}
+
+/// Removes comments, keeping directives like `--!strict` that come before
+/// any code.
+fn strip_comments(tokens: &mut Vec<Token>) {
+    let first_code = tokens
+        .iter()
+        .position(|token| !token.is_trivia())
+        .unwrap_or(tokens.len());
+
+    let mut stripped: Vec<Token> = Vec::with_capacity(tokens.len());
+
+    for (index, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Comment || (index < first_code && token.text.starts_with("--!"))
+        {
+            stripped.push(token.clone());
+            continue;
+        }
+
+        // A comment can be all that separates two tokens, like in
+        // `return--[[ comment ]]value`, so it's replaced with a space there.
+        let next = tokens[index + 1..]
+            .iter()
+            .find(|token| token.kind != TokenKind::Comment);
+
+        if let (Some(previous), Some(next)) = (stripped.last(), next) {
+            if previous.kind != TokenKind::Whitespace
+                && next.kind != TokenKind::Whitespace
+                && needs_space(previous, next)
+            {
+                stripped.push(Token {
+                    kind: TokenKind::Whitespace,
+                    text: Cow::Borrowed(" "),
+                });
+            }
+        }
+    }
+
+    *tokens = stripped;
+}
+
+/// Puts the tokens back together with as little whitespace as possible.
+fn minify(tokens: &[Token]) -> String {
+    let mut output = String::new();
+    let mut previous: Option<&Token> = None;
+    let mut had_newline = false;
+
+    for token in tokens {
+        if token.kind == TokenKind::Whitespace {
+            had_newline |= token.text.contains('\n');
+            continue;
+        }
+
+        if let Some(previous) = previous {
+            // Comments run until the end of the line, and a call on a new line
+            // would be ambiguous, so those newlines are kept.
+            if previous.kind == TokenKind::Comment || (had_newline && token.is("(")) {
+                output.push('\n');
+            } else if needs_space(previous, token) {
+                output.push(' ');
+            }
+        }
+
+        output.push_str(&token.text);
+        previous = Some(token);
+        had_newline = false;
+    }
+
+    output
+}
+
+/// Tells whether two tokens would turn into different tokens if they were
+/// written next to each other without any space.
+fn needs_space(left: &Token, right: &Token) -> bool {
+    let joined = format!("{}{}", left.text, right.text);
+
+    match tokenize(&joined) {
+        Ok(tokens) => tokens.len() != 2 || tokens[0].text != left.text,
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn transforms(defines: &[(&str, DefineValue)]) -> LuauTransforms {
+        LuauTransforms {
+            defines: defines
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            ..LuauTransforms::default()
+        }
+    }
+
+    #[test]
+    fn tokenize_round_trip() {
+        let source = "--!strict\n\
+            local a = [==[long ]] string]==] .. \"q\\\"uote\" .. 'single'\n\
+            --[[ long\ncomment ]] local b = `value: {a .. `nested {1}`}` -- trailing\n\
+            local c = 0x1F + 1e-3 + .5 // 2\n";
+
+        let tokens = tokenize(source).unwrap();
+        let joined: String = tokens.iter().map(|token| token.text.as_ref()).collect();
+
+        assert_eq!(joined, source);
+        assert!(tokens
+            .iter()
+            .any(|token| token.kind == TokenKind::String && token.text.starts_with('`')));
+        assert!(tokens.iter().any(|token| token.is("//")));
+    }
+
+    #[test]
+    fn unfinished_string() {
+        let error = tokenize("local a = 1\nlocal b = \"oops\n").unwrap_err();
+        assert_eq!(error.to_string(), "Unfinished string on line 2");
+    }
+
+    #[test]
+    fn inline_defines() {
+        let source = "local DEV = true --!rojo-define\n\
+            local NAME: string = \"dev\" --!rojo-define\n\
+            print(DEV, NAME, config.DEV)\n";
+
+        let output = transforms(&[("NAME", DefineValue::String("release".to_owned()))])
+            .apply(source, &DeclaredConstants::default())
+            .unwrap();
+
+        assert_eq!(output, "\n\nprint(true, \"release\", config.DEV)\n");
+    }
+
+    #[test]
+    fn inline_special_values() {
+        let source = "local OFFSET = -1 --!rojo-define\n\
+            local LIMIT = 10 --!rojo-define\n\
+            print(5-OFFSET, `limit: {LIMIT}, nested: {`{OFFSET}`}`)\n";
+
+        let output = transforms(&[("LIMIT", DefineValue::Number(f64::INFINITY))])
+            .apply(source, &DeclaredConstants::default())
+            .unwrap();
+
+        assert_eq!(
+            output,
+            "\n\nprint(5-(-1), `limit: {(math.huge)}, nested: {`{(-1)}`}`)\n"
+        );
+    }
+
+    #[test]
+    fn redeclared_constants() {
+        let cases = [
+            ("function f(DEV) end", 2),
+            ("local f = function(a, DEV: {string}) end", 2),
+            ("for _, DEV in t do end", 2),
+            ("for DEV = 1, 10 do end", 2),
+            ("print(DEV)\nlocal DEV = x", 3),
+            ("local a: {[string]: number}, DEV = {}, 1", 2),
+            ("local function DEV() end", 2),
+            ("local DEV = false --!rojo-define", 2),
+        ];
+
+        for (body, line) in cases {
+            let source = format!("local DEV = true --!rojo-define\n{}\n", body);
+            let error = LuauTransforms::default()
+                .apply(&source, &DeclaredConstants::default())
+                .unwrap_err();
+
+            assert!(
+                error.to_string().contains(&format!("line {}", line)),
+                "{}: {}",
+                body,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn other_names_are_not_redeclarations() {
+        let source = "local DEV = true --!rojo-define\n\
+            local a: number\n\
+            print(DEV)\n\
+            local function f(x: Map<string, number>, ...) return x.DEV end\n\
+            for i, v in pairs({ DEV = DEV }) do end\n";
+
+        let output = LuauTransforms::default()
+            .apply(source, &DeclaredConstants::default())
+            .unwrap();
+
+        assert_eq!(
+            output,
+            "\nlocal a: number\n\
+             print(true)\n\
+             local function f(x: Map<string, number>, ...) return x.DEV end\n\
+             for i, v in pairs({ DEV = true }) do end\n"
+        );
+    }
+
+    #[test]
+    fn unsupported_declarations() {
+        let cases = [
+            "local NAMES: {string} = {} --!rojo-define",
+            "local DEV = not false --!rojo-define",
+            "DEV = true --!rojo-define",
+            "--!rojo-define",
+        ];
+
+        for source in cases {
+            let error = LuauTransforms::default()
+                .apply(source, &DeclaredConstants::default())
+                .unwrap_err();
+            assert!(
+                error.to_string().contains("line 1"),
+                "{}: {}",
+                source,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn undeclared_defines() {
+        let declared = DeclaredConstants::default();
+        transforms(&[])
+            .apply("local DECLARED_IN_TEST = 1 --!rojo-define\n", &declared)
+            .unwrap();
+
+        let profile = transforms(&[
+            ("DECLARED_IN_TEST", DefineValue::Number(2.0)),
+            ("NEVER_DECLARED_IN_TEST", DefineValue::Bool(true)),
+        ]);
+
+        assert_eq!(
+            profile.undeclared_defines(&declared),
+            ["NEVER_DECLARED_IN_TEST"]
+        );
+
+        // Names declared while applying other transforms aren't counted.
+        assert_eq!(
+            profile.undeclared_defines(&DeclaredConstants::default()),
+            ["DECLARED_IN_TEST", "NEVER_DECLARED_IN_TEST"]
+        );
+    }
+
+    #[test]
+    fn remove_dev_blocks() {
+        let source = "local DEV = true --!rojo-define\n\
+            if DEV then\n\
+            \tprint(\"dev\")\n\
+            \tlocal x = if DEV then 1 else 2\n\
+            end\n\
+            if not DEV then print(\"release\") end\n\
+            if DEV then a() elseif b then c() else d() end\n";
+
+        let output = transforms(&[("DEV", DefineValue::Bool(false))])
+            .apply(source, &DeclaredConstants::default())
+            .unwrap();
+
+        assert_eq!(
+            output,
+            "\n\n\
+             do print(\"release\") end\n\
+             if b then c() else d() end\n"
+        );
+    }
+
+    #[test]
+    fn remove_blocks_for_string_constants() {
+        let source = "local MODE = 'dev' --!rojo-define\n\
+            local NAME = [[dev]] --!rojo-define\n\
+            if MODE then a() else b() end\n\
+            if not NAME then c() else d() end\n";
+
+        let output = LuauTransforms::default()
+            .apply(source, &DeclaredConstants::default())
+            .unwrap();
+
+        assert_eq!(output, "\n\ndo a() end\ndo d() end\n");
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let source = "local DEV = false --!rojo-define\n\
+            if DEV then\n\
+            \tfor i = 1, 10 do\n\
+            \t\tif i > 5 then break end\n\
+            \tend\n\
+            \tlocal f = function() return if i then 1 elseif j then 2 else 3 end\n\
+            else\n\
+            \tprint(\"release\")\n\
+            end\n";
+
+        let output = LuauTransforms::default()
+            .apply(source, &DeclaredConstants::default())
+            .unwrap();
+
+        assert_eq!(output, "\ndo\n\tprint(\"release\")\nend\n");
+    }
+
+    #[test]
+    fn strip_comments_and_minify() {
+        let source = "--!strict\n\
+            -- A helpful comment\n\
+            local value = 1 -- the value\n\
+            --[[ a long\ncomment ]]\n\
+            local other = value - -value\n\
+            print(other .. 1, \"--not a comment\")\n\
+            ;(print)(\"called\")\n\
+            print(1 .. 2)\n\
+            (print)(\"ambiguous\")\n\
+            return--[[ separator ]]value\n";
+
+        let stripped = LuauTransforms {
+            strip_comments: true,
+            ..LuauTransforms::default()
+        }
+        .apply(source, &DeclaredConstants::default())
+        .unwrap();
+
+        assert_eq!(
+            stripped,
+            "--!strict\n\n\
+             local value = 1 \n\
+             \n\
+             local other = value - -value\n\
+             print(other .. 1, \"--not a comment\")\n\
+             ;(print)(\"called\")\n\
+             print(1 .. 2)\n\
+             (print)(\"ambiguous\")\n\
+             return value\n"
+        );
+
+        let minified = LuauTransforms {
+            minify: true,
+            ..LuauTransforms::default()
+        }
+        .apply(source, &DeclaredConstants::default())
+        .unwrap();
+
+        assert_eq!(
+            minified,
+            "--!strict\n\
+             local value=1 local other=value- -value print(other..1,\"--not a comment\");\
+             (print)(\"called\")print(1 ..2)\n\
+             (print)(\"ambiguous\")return value"
+        );
+    }
+}
@@ -10,7 +10,13 @@ use memofs::Vfs;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::{glob::Glob, resolution::UnresolvedValue, snapshot::SyncRule};
+use crate::{
+    glob::Glob,
+    luau_transform::LuauTransforms,
+    resolution::UnresolvedValue,
+    snapshot::{DataModuleOptions, IgnoreUnknownInstances, InitFileRule, SyncRule},
+    snapshot_middleware::Middleware,
+};
 
 static PROJECT_FILENAME: &str = "default.project.json";
 
@@ -47,6 +53,18 @@ enum Error {
         source: serde_json::Error,
         path: PathBuf,
     },
+
+    #[error("Instance \"{instance}\" uses the template \"{template}\", which does not exist.\n\
+            Project path: {}", .path.display())]
+    UnknownTemplate {
+        template: String,
+        instance: String,
+        path: PathBuf,
+    },
+
+    #[error("The template \"{template}\" uses itself, either directly or through another template.\n\
+            Project path: {}", .path.display())]
+    RecursiveTemplate { template: String, path: PathBuf },
 }
 
 /// Contains all of the configuration for a Rojo-managed project.
@@ -99,6 +117,38 @@ pub struct Project {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub emit_legacy_scripts: Option<bool>,
 
+    /// If set, Rojo will refuse to build instances whose class, property
+    /// names, or property types aren't in the reflection database, instead
+    /// of passing them through to Roblox.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+
+    /// If set, escapes like `%2F` in file and folder names are decoded, so
+    /// that instances can have names that aren't allowed in file names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escaped_file_names: Option<bool>,
+
+    /// Controls the ModuleScripts generated from data files like JSON, TOML,
+    /// and YAML: whether they export a Luau type for their data and whether
+    /// that data is frozen. Nested projects inherit these options.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_modules: Option<DataModuleOptions>,
+
+    /// If specified, the path to an asset manifest, relative to the folder the
+    /// project file is in. The manifest maps files in the project to asset IDs
+    /// so that `Content` properties and data files can refer to them with
+    /// `@asset/path/to/file`. Nested projects inherit it unless they specify
+    /// their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_manifest: Option<PathBuf>,
+
+    /// Named sets of transforms, like stripping comments and inlining
+    /// constants, that are applied to scripts when building with
+    /// `rojo build --profile <name>`. Only the root project's profiles are
+    /// used.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub build_profiles: BTreeMap<String, LuauTransforms>,
+
     /// A list of globs, relative to the folder the project file is in, that
     /// match files that should be excluded if Rojo encounters them.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -110,6 +160,30 @@ pub struct Project {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub sync_rules: Vec<SyncRule>,
 
+    /// A list of file names that should be treated as the 'init' file of the
+    /// directory they're in, along with the middleware used for them. These
+    /// take priority over the built-in `init` files like `init.luau`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub init_files: Vec<InitFileRule>,
+
+    /// Default properties for every instance of a given class, keyed by class
+    /// name. These also apply to subclasses of the class, and are overridden
+    /// by properties set on the instance by any other means.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub class_defaults: BTreeMap<String, HashMap<String, UnresolvedValue>>,
+
+    /// Reusable trees of instances, keyed by name. A node in the tree can
+    /// create a copy of one of these by setting `$template` to its name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, ProjectNode>,
+
+    /// A path, relative to the folder the project file is in, to a reflection
+    /// database to use instead of the one bundled with Rojo. This is only
+    /// used for the root project, and the `--reflection-database` flag takes
+    /// priority over it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reflection_database: Option<PathBuf>,
+
     /// The path to the file that this project came from. Relative paths in the
     /// project should be considered relative to the parent of this field, also
     /// given by `Project::folder_location`.
@@ -157,7 +231,9 @@ impl Project {
 
     /// Sets the name of a project. The order it handles is as follows:
     ///
-    /// - If the project is a `default.project.json`, uses the folder's name
+    /// - If the project is a `default.project.json` or is named by one of the
+    ///   project's `initFiles` that uses the `project` middleware, uses the
+    ///   folder's name
     /// - If a fallback is specified, uses that blindly
     /// - Otherwise, loops through sync rules (including the default ones!) and
     ///   uses the name of the first one that matches and is a project file
@@ -170,9 +246,13 @@ impl Project {
                 path: self.file_location.clone(),
             })?;
 
-        // If you're editing this to be generic, make sure you also alter the
-        // snapshot middleware to support generic init paths.
-        if file_name == PROJECT_FILENAME {
+        let is_init_file = file_name == PROJECT_FILENAME
+            || self
+                .init_files
+                .iter()
+                .any(|rule| rule.middleware == Middleware::Project && rule.name == file_name);
+
+        if is_init_file {
             let folder_name = self.folder_location().file_name().and_then(OsStr::to_str);
             if let Some(folder_name) = folder_name {
                 self.name = Some(folder_name.to_string());
@@ -269,6 +349,58 @@ impl Project {
     pub fn folder_location(&self) -> &Path {
         self.file_location.parent().unwrap()
     }
+
+    /// Returns a copy of this project's tree with every `$template` replaced
+    /// by the template it refers to, merged with the node that used it.
+    pub fn expand_templates(&self) -> Result<ProjectNode, ProjectError> {
+        let name = self.name.as_deref().unwrap_or_default();
+        let mut stack = Vec::new();
+
+        Ok(self.expand_node(name, &self.tree, &mut stack)?)
+    }
+
+    fn expand_node<'a>(
+        &'a self,
+        instance_name: &str,
+        node: &ProjectNode,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<ProjectNode, Error> {
+        let mut expanded = match &node.template {
+            Some(template_name) => {
+                let (template_name, template) = self
+                    .templates
+                    .get_key_value(template_name)
+                    .ok_or_else(|| Error::UnknownTemplate {
+                        template: template_name.clone(),
+                        instance: instance_name.to_owned(),
+                        path: self.file_location.clone(),
+                    })?;
+
+                if stack.contains(&template_name.as_str()) {
+                    return Err(Error::RecursiveTemplate {
+                        template: template_name.clone(),
+                        path: self.file_location.clone(),
+                    });
+                }
+
+                stack.push(template_name);
+                let base = self.expand_node(instance_name, template, stack)?;
+                stack.pop();
+
+                let mut merged = base.merge(node.clone());
+                merged.template = None;
+                merged.expanded_from = Some(template_name.clone());
+                merged
+            }
+            None => node.clone(),
+        };
+
+        for (child_name, child) in &mut expanded.children {
+            *child = self.expand_node(child_name, child, stack)?;
+        }
+
+        Ok(expanded)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -283,19 +415,26 @@ impl OptionalPathNode {
     }
 }
 
-/// Describes a path that is either optional or required
+/// Describes a path that is either optional or required, or a list of paths
+/// whose instances should all be merged into one node.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum PathNode {
     Required(#[serde(serialize_with = "crate::path_serializer::serialize_absolute")] PathBuf),
     Optional(OptionalPathNode),
+    Multiple(
+        #[serde(serialize_with = "crate::path_serializer::serialize_vec_absolute")] Vec<PathBuf>,
+    ),
 }
 
 impl PathNode {
-    pub fn path(&self) -> &Path {
+    /// Returns all of the paths described by this node. Any of these paths
+    /// may be a glob.
+    pub fn paths(&self) -> &[PathBuf] {
         match self {
-            PathNode::Required(pathbuf) => pathbuf,
-            PathNode::Optional(OptionalPathNode { optional }) => optional,
+            PathNode::Required(pathbuf) => std::slice::from_ref(pathbuf),
+            PathNode::Optional(OptionalPathNode { optional }) => std::slice::from_ref(optional),
+            PathNode::Multiple(paths) => paths,
         }
     }
 }
@@ -349,6 +488,10 @@ pub struct ProjectNode {
     /// If set to `false`, Rojo will destroy any instances it does not
     /// recognize.
     ///
+    /// If set to a list of patterns, only unknown instances that match one of
+    /// them will be left alone. Patterns match an instance's name, like
+    /// `"Terrain"`, or its class, like `"class:Camera"`.
+    ///
     /// If unset, its default value depends on other settings:
     /// - If `$path` is not set, defaults to `true`
     /// - If `$path` is set, defaults to `false`
@@ -356,17 +499,70 @@ pub struct ProjectNode {
         rename = "$ignoreUnknownInstances",
         skip_serializing_if = "Option::is_none"
     )]
-    pub ignore_unknown_instances: Option<bool>,
+    pub ignore_unknown_instances: Option<IgnoreUnknownInstances>,
 
     /// Defines that this instance should come from the given file path. This
     /// path can point to any file type supported by Rojo, including Lua files
-    /// (`.lua`), Roblox models (`.rbxm`, `.rbxmx`), and localization table
-    /// spreadsheets (`.csv`).
+    /// (`.lua`), Roblox models (`.rbxm`, `.rbxmx`), Roblox places (`.rbxl`,
+    /// `.rbxlx`), and localization table spreadsheets (`.csv`).
+    ///
+    /// If `$path` is a glob or a list of paths, the instance is a Folder
+    /// instead and every matching path becomes one of its children.
     #[serde(rename = "$path", skip_serializing_if = "Option::is_none")]
     pub path: Option<PathNode>,
+
+    /// If set, only the descendant of the instance from `$path` with this
+    /// path is used, like `"Workspace"` or `"Workspace/Map"`. This is mostly
+    /// useful for pulling a service or subtree out of a place file.
+    ///
+    /// `$select` can only be used with a single `$path`.
+    #[serde(rename = "$select", skip_serializing_if = "Option::is_none")]
+    pub select: Option<String>,
+
+    /// If set, this instance is created from the template with the given name
+    /// in the project's `templates`. Anything else set on this node overrides
+    /// what the template specifies, and children are merged by name.
+    #[serde(rename = "$template", skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// The name of the template this node was created from, if any. This is
+    /// only used to improve error messages.
+    #[serde(skip)]
+    pub expanded_from: Option<String>,
 }
 
 impl ProjectNode {
+    /// Merges `other` on top of this node. Fields set on `other` take
+    /// priority, and children with the same name are merged recursively.
+    fn merge(mut self, other: ProjectNode) -> ProjectNode {
+        for (name, child) in other.children {
+            let merged = match self.children.remove(&name) {
+                Some(existing) => existing.merge(child),
+                None => child,
+            };
+
+            self.children.insert(name, merged);
+        }
+
+        self.properties.extend(other.properties);
+        self.attributes.extend(other.attributes);
+
+        ProjectNode {
+            class_name: other.class_name.or(self.class_name),
+            id: other.id.or(self.id),
+            children: self.children,
+            properties: self.properties,
+            attributes: self.attributes,
+            ignore_unknown_instances: other
+                .ignore_unknown_instances
+                .or(self.ignore_unknown_instances),
+            path: other.path.or(self.path),
+            select: other.select.or(self.select),
+            template: other.template.or(self.template),
+            expanded_from: other.expanded_from.or(self.expanded_from),
+        }
+    }
+
     fn validate_reserved_names(&self) {
         for (name, child) in &self.children {
             if name.starts_with('$') {
@@ -435,6 +631,24 @@ mod test {
         );
     }
 
+    #[test]
+    fn project_node_multiple() {
+        let project_node: ProjectNode = serde_json::from_str(
+            r#"{
+                "$path": ["shared", "packages/*/src"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            project_node.path,
+            Some(PathNode::Multiple(vec![
+                PathBuf::from("shared"),
+                PathBuf::from("packages/*/src")
+            ]))
+        );
+    }
+
     #[test]
     fn project_node_none() {
         let project_node: ProjectNode = serde_json::from_str(
@@ -485,4 +699,119 @@ mod test {
         let serialized = serde_json::to_string(&project_node).unwrap();
         assert_eq!(serialized, r#"{"$path":"../src"}"#);
     }
+
+    fn project_from_str(contents: &str) -> Project {
+        Project::load_from_slice(
+            contents.as_bytes(),
+            PathBuf::from("/foo/default.project.json"),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn expand_templates_merges_overrides() {
+        let project = project_from_str(
+            r#"{
+                "templates": {
+                    "Zone": {
+                        "$className": "Model",
+                        "$attributes": { "Difficulty": 1 },
+                        "Spawn": {
+                            "$className": "SpawnLocation",
+                            "$properties": { "Anchored": true }
+                        }
+                    }
+                },
+                "tree": {
+                    "$className": "Folder",
+                    "Forest": {
+                        "$template": "Zone",
+                        "$attributes": { "Difficulty": 2 },
+                        "Spawn": {
+                            "$properties": { "Position": [1, 2, 3] }
+                        },
+                        "Trees": { "$className": "Folder" }
+                    }
+                }
+            }"#,
+        );
+
+        let tree = project.expand_templates().unwrap();
+        let forest = &tree.children["Forest"];
+
+        assert_eq!(forest.class_name.as_deref(), Some("Model"));
+        assert_eq!(forest.template, None);
+        assert_eq!(forest.expanded_from.as_deref(), Some("Zone"));
+        assert_eq!(
+            forest.attributes["Difficulty"],
+            serde_json::from_str("2").unwrap()
+        );
+
+        let spawn = &forest.children["Spawn"];
+        assert_eq!(spawn.class_name.as_deref(), Some("SpawnLocation"));
+        assert_eq!(spawn.properties.len(), 2);
+
+        assert!(forest.children.contains_key("Trees"));
+    }
+
+    #[test]
+    fn expand_templates_nested() {
+        let project = project_from_str(
+            r#"{
+                "templates": {
+                    "Base": { "$className": "Model" },
+                    "Derived": {
+                        "$template": "Base",
+                        "Child": { "$template": "Base" }
+                    }
+                },
+                "tree": {
+                    "$template": "Derived"
+                }
+            }"#,
+        );
+
+        let tree = project.expand_templates().unwrap();
+
+        assert_eq!(tree.class_name.as_deref(), Some("Model"));
+        assert_eq!(tree.expanded_from.as_deref(), Some("Derived"));
+        assert_eq!(tree.children["Child"].class_name.as_deref(), Some("Model"));
+    }
+
+    #[test]
+    fn expand_templates_unknown() {
+        let project = project_from_str(
+            r#"{
+                "tree": {
+                    "$className": "Folder",
+                    "Forest": { "$template": "Zone" }
+                }
+            }"#,
+        );
+
+        let err = project.expand_templates().unwrap_err().to_string();
+        assert!(err.contains("\"Forest\""), "{}", err);
+        assert!(err.contains("\"Zone\""), "{}", err);
+    }
+
+    #[test]
+    fn expand_templates_recursive() {
+        let project = project_from_str(
+            r#"{
+                "templates": {
+                    "Zone": {
+                        "$className": "Model",
+                        "Inner": { "$template": "Zone" }
+                    }
+                },
+                "tree": {
+                    "$template": "Zone"
+                }
+            }"#,
+        );
+
+        let err = project.expand_templates().unwrap_err().to_string();
+        assert!(err.contains("uses itself"), "{}", err);
+    }
 }
@@ -0,0 +1,161 @@
+//! Provides the reflection database used to resolve properties, enums, and
+//! classes. Rojo bundles a database, but it can be swapped for a newer one at
+//! runtime so that new Roblox APIs can be used without a new Rojo release.
+
+use std::{path::Path, sync::RwLock};
+
+use anyhow::Context;
+use rbx_reflection::{PropertyDescriptor, ReflectionDatabase};
+
+/// Custom reflection databases that have been loaded. One given on the command
+/// line always takes priority over one loaded from a project, since projects
+/// are loaded again for every serve session but the command line isn't.
+struct CustomDatabases {
+    command_line: Option<&'static ReflectionDatabase<'static>>,
+    project: Option<&'static ReflectionDatabase<'static>>,
+}
+
+impl CustomDatabases {
+    fn current(&self) -> Option<&'static ReflectionDatabase<'static>> {
+        self.command_line.or(self.project)
+    }
+}
+
+static CUSTOM_DATABASES: RwLock<CustomDatabases> = RwLock::new(CustomDatabases {
+    command_line: None,
+    project: None,
+});
+
+/// Returns the reflection database that Rojo should use, which is either a
+/// custom one that was loaded or the one bundled with Rojo.
+pub fn database() -> &'static ReflectionDatabase<'static> {
+    CUSTOM_DATABASES
+        .read()
+        .unwrap()
+        .current()
+        .unwrap_or_else(rbx_reflection_database::get)
+}
+
+/// Finds the descriptor for a property on the given class or any of its
+/// superclasses.
+pub fn find_property_descriptor(
+    class_name: &str,
+    prop_name: &str,
+) -> Option<&'static PropertyDescriptor<'static>> {
+    let database = database();
+    let mut current_class_name = class_name;
+
+    loop {
+        let class = database.classes.get(current_class_name)?;
+        if let Some(descriptor) = class.properties.get(prop_name) {
+            return Some(descriptor);
+        }
+
+        current_class_name = class.superclass.as_deref()?;
+    }
+}
+
+/// Tells whether a reflection database was given on the command line.
+pub fn has_command_line_database() -> bool {
+    CUSTOM_DATABASES.read().unwrap().command_line.is_some()
+}
+
+/// Replaces the reflection database with the one in the given file, which can
+/// be a MessagePack file like the one bundled with Rojo or a JSON dump.
+pub fn load_command_line_database(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let database = load_database(path, contents)?;
+    CUSTOM_DATABASES.write().unwrap().command_line = Some(database);
+
+    Ok(())
+}
+
+/// Uses the reflection database in the given file for the project being
+/// served or built, replacing any that was loaded for a previous project. A
+/// database given on the command line still takes priority over it.
+pub fn load_project_database(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let database = load_database(path, contents)?;
+    CUSTOM_DATABASES.write().unwrap().project = Some(database);
+
+    Ok(())
+}
+
+/// Stops using the reflection database loaded for a previous project, for
+/// projects that don't have a `reflectionDatabase`.
+pub fn clear_project_database() {
+    CUSTOM_DATABASES.write().unwrap().project = None;
+}
+
+fn load_database(
+    path: &Path,
+    contents: &[u8],
+) -> anyhow::Result<&'static ReflectionDatabase<'static>> {
+    let database = decode_database(path, contents)
+        .with_context(|| format!("Could not load reflection database {}", path.display()))?;
+
+    log::info!(
+        "Using reflection database {} (Roblox version {})",
+        path.display(),
+        database.version.map(|part| part.to_string()).join(".")
+    );
+
+    // Databases are only loaded once per process or serve session and need to
+    // live for as long as the bundled one does, so leaking them is fine.
+    Ok(Box::leak(Box::new(database)))
+}
+
+fn decode_database(path: &Path, contents: &[u8]) -> anyhow::Result<ReflectionDatabase<'static>> {
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        Ok(serde_json::from_slice(contents)?)
+    } else {
+        Ok(rmp_serde::from_slice(contents)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_json_database() {
+        let contents = r#"{
+            "Version": [0, 650, 0, 1],
+            "Classes": {},
+            "Enums": {}
+        }"#;
+
+        let database = decode_database(Path::new("database.json"), contents.as_bytes()).unwrap();
+        assert_eq!(database.version, [0, 650, 0, 1]);
+    }
+
+    #[test]
+    fn command_line_database_takes_priority() {
+        let command_line = rbx_reflection_database::get();
+        let project: &'static ReflectionDatabase<'static> =
+            Box::leak(Box::new(ReflectionDatabase::new()));
+
+        let mut databases = CustomDatabases {
+            command_line: None,
+            project: None,
+        };
+        assert!(databases.current().is_none());
+
+        databases.project = Some(project);
+        assert!(std::ptr::eq(databases.current().unwrap(), project));
+
+        databases.command_line = Some(command_line);
+        assert!(std::ptr::eq(databases.current().unwrap(), command_line));
+    }
+
+    #[test]
+    fn decode_msgpack_database() {
+        let contents = rmp_serde::to_vec(rbx_reflection_database::get()).unwrap();
+
+        let database = decode_database(Path::new("database.msgpack"), &contents).unwrap();
+        assert_eq!(database.version, rbx_reflection_database::get().version);
+        assert!(database.classes.contains_key("Part"));
+    }
+}
@@ -1,14 +1,20 @@
-use std::borrow::Borrow;
+use std::{
+    borrow::Borrow,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{bail, format_err};
+use anyhow::{bail, format_err, Context};
+use memofs::Vfs;
 use rbx_dom_weak::types::{
-    Attributes, CFrame, Color3, Content, Enum, Font, MaterialColors, Matrix3, Tags, Variant,
-    VariantType, Vector2, Vector3,
+    Attributes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
+    ColorSequenceKeypoint, Content, CustomPhysicalProperties, Enum, Font, MaterialColors, Matrix3,
+    NumberRange, NumberSequence, NumberSequenceKeypoint, PhysicalProperties, Rect, Tags, UDim,
+    UDim2, Variant, VariantType, Vector2, Vector3,
 };
-use rbx_reflection::{DataType, PropertyDescriptor};
+use rbx_reflection::DataType;
 use serde::{Deserialize, Serialize};
 
-use crate::REF_POINTER_ATTRIBUTE_PREFIX;
+use crate::{reflection::find_property_descriptor, RefPath, REF_POINTER_ATTRIBUTE_PREFIX};
 
 /// A user-friendly version of `Variant` that supports specifying ambiguous
 /// values. Ambiguous values need a reflection database to be resolved to a
@@ -16,16 +22,96 @@ use crate::REF_POINTER_ATTRIBUTE_PREFIX;
 ///
 /// This type is used in Rojo projects and JSON models to make specifying the
 /// most common types of properties, like strings or vectors, much easier.
+///
+/// Values can also refer to a file on disk, whose contents must be read with
+/// `load_file` before the value can be resolved.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum UnresolvedValue {
+    File(FileValue),
     FullyQualified(Variant),
+    Path(PathValue),
     Ambiguous(AmbiguousValue),
 }
 
 impl UnresolvedValue {
+    /// If this value refers to a file, reads that file through the given Vfs
+    /// and replaces this value with the file's contents. Relative paths are
+    /// resolved against `base_path`.
+    ///
+    /// Returns the path of the file that was read, if any, so that callers can
+    /// mark it as relevant to the instance being snapshotted.
+    pub fn load_file(&mut self, vfs: &Vfs, base_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+        let file_value = match self {
+            UnresolvedValue::File(file_value) => file_value,
+            _ => return Ok(None),
+        };
+
+        let path = base_path.join(file_value.path());
+        let contents = vfs
+            .read(&path)
+            .with_context(|| format!("Could not read property file {}", path.display()))?;
+
+        *self = match file_value {
+            FileValue::Text(_) => {
+                let contents = String::from_utf8(contents.to_vec()).with_context(|| {
+                    format!("Property file was not valid UTF-8: {}", path.display())
+                })?;
+
+                UnresolvedValue::Ambiguous(AmbiguousValue::String(contents))
+            }
+            FileValue::Binary(_) => {
+                UnresolvedValue::FullyQualified(BinaryString::from(contents.to_vec()).into())
+            }
+        };
+
+        Ok(Some(path))
+    }
+
+    /// If this value points to another instance by its path, returns that
+    /// path. Strings given for Ref properties are treated as paths.
+    ///
+    /// Paths can't be turned into a `Variant` until the instance they point to
+    /// exists, so they need to be handled before calling `resolve`.
+    pub fn ref_path(&self, class_name: &str, prop_name: &str) -> anyhow::Result<Option<RefPath>> {
+        let is_ref = || {
+            matches!(
+                find_property_descriptor(class_name, prop_name).map(|property| &property.data_type),
+                Some(DataType::Value(VariantType::Ref))
+            )
+        };
+
+        match self {
+            UnresolvedValue::Path(value) => {
+                if !is_ref() {
+                    bail!(
+                        "Cannot use the path {} for property {}.{}, which is not a Ref property",
+                        value.path,
+                        class_name,
+                        prop_name
+                    );
+                }
+
+                Ok(Some(value.path.clone()))
+            }
+            UnresolvedValue::Ambiguous(AmbiguousValue::String(value)) if is_ref() => {
+                let path = value.parse().with_context(|| {
+                    format!(
+                        "Invalid value for Ref property {}.{}",
+                        class_name, prop_name
+                    )
+                })?;
+
+                Ok(Some(path))
+            }
+            _ => Ok(None),
+        }
+    }
+
     pub fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
         match self {
+            UnresolvedValue::File(file_value) => file_value.unloaded_error(),
+            UnresolvedValue::Path(value) => value.unsupported_error(),
             UnresolvedValue::FullyQualified(full) => Ok(full),
             UnresolvedValue::Ambiguous(partial) => partial.resolve(class_name, prop_name),
         }
@@ -33,12 +119,61 @@ impl UnresolvedValue {
 
     pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
         match self {
+            UnresolvedValue::File(file_value) => file_value.unloaded_error(),
+            UnresolvedValue::Path(value) => value.unsupported_error(),
             UnresolvedValue::FullyQualified(full) => Ok(full),
             UnresolvedValue::Ambiguous(partial) => partial.resolve_unambiguous(),
         }
     }
 }
 
+/// A value whose contents live in a separate file, like
+/// `{ "$file": "description.txt" }`.
+///
+/// Text files resolve like a string would, while binary files always become a
+/// `BinaryString`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FileValue {
+    #[serde(rename = "$file")]
+    Text(PathBuf),
+
+    #[serde(rename = "$binaryFile")]
+    Binary(PathBuf),
+}
+
+impl FileValue {
+    pub fn path(&self) -> &Path {
+        match self {
+            FileValue::Text(path) | FileValue::Binary(path) => path,
+        }
+    }
+
+    fn unloaded_error(&self) -> anyhow::Result<Variant> {
+        bail!(
+            "The file {} was not read before resolving its value. This is a Rojo bug!",
+            self.path().display()
+        )
+    }
+}
+
+/// A reference to another instance by its path, like
+/// `{ "Ref": "./Handle" }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathValue {
+    #[serde(rename = "Ref")]
+    pub path: RefPath,
+}
+
+impl PathValue {
+    fn unsupported_error(&self) -> anyhow::Result<Variant> {
+        bail!(
+            "The Ref path {} cannot be used here. Ref paths can only be used \
+             for properties in projects, meta files, and JSON models",
+            self.path
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum AmbiguousValue {
@@ -49,7 +184,9 @@ pub enum AmbiguousValue {
     Array2([f64; 2]),
     Array3([f64; 3]),
     Array4([f64; 4]),
+    Array5([f64; 5]),
     Array12([f64; 12]),
+    NumberArrays(Vec<Vec<f64>>),
     Attributes(Attributes),
     Font(Font),
     MaterialColors(MaterialColors),
@@ -57,12 +194,12 @@ pub enum AmbiguousValue {
 
 impl AmbiguousValue {
     pub fn resolve(self, class_name: &str, prop_name: &str) -> anyhow::Result<Variant> {
-        let property = find_descriptor(class_name, prop_name)
+        let property = find_property_descriptor(class_name, prop_name)
             .ok_or_else(|| format_err!("Unknown property {}.{}", class_name, prop_name))?;
 
         match &property.data_type {
             DataType::Enum(enum_name) => {
-                let database = rbx_reflection_database::get();
+                let database = crate::reflection::database();
 
                 let enum_descriptor = database.enums.get(enum_name).ok_or_else(|| {
                     format_err!("Unknown enum {}. This is a Rojo bug!", enum_name)
@@ -130,15 +267,107 @@ impl AmbiguousValue {
                 }
 
                 (VariantType::CFrame, AmbiguousValue::Array12(value)) => {
-                    let value = value.map(|v| v as f32);
-                    let pos = Vector3::new(value[0], value[1], value[2]);
-                    let orientation = Matrix3::new(
-                        Vector3::new(value[3], value[4], value[5]),
-                        Vector3::new(value[6], value[7], value[8]),
-                        Vector3::new(value[9], value[10], value[11]),
-                    );
+                    Ok(array_to_cframe(value).into())
+                }
+
+                (VariantType::Color3, AmbiguousValue::String(value)) => {
+                    let color = parse_hex_color(&value)?;
+                    Ok(Color3::from(color).into())
+                }
+                (VariantType::Color3uint8, AmbiguousValue::String(value)) => {
+                    Ok(parse_hex_color(&value)?.into())
+                }
+
+                (VariantType::BrickColor, AmbiguousValue::String(value)) => {
+                    let color = BrickColor::from_name(&value)
+                        .ok_or_else(|| format_err!("Unknown BrickColor name '{}'", value))?;
+                    Ok(color.into())
+                }
+                (VariantType::BrickColor, AmbiguousValue::Number(value)) => {
+                    if value.fract() != 0.0 || !(0.0..=u16::MAX as f64).contains(&value) {
+                        bail!(
+                            "Invalid BrickColor number {} for property {}.{}. \
+                             BrickColor numbers must be whole numbers from 0 to {}",
+                            value,
+                            class_name,
+                            prop_name,
+                            u16::MAX
+                        );
+                    }
+
+                    let color = BrickColor::from_number(value as u16)
+                        .ok_or_else(|| format_err!("Unknown BrickColor number {}", value))?;
+                    Ok(color.into())
+                }
 
-                    Ok(CFrame::new(pos, orientation).into())
+                (VariantType::UDim, AmbiguousValue::Array2(value)) => {
+                    let offset = udim_offset(value[1], class_name, prop_name)?;
+                    Ok(UDim::new(value[0] as f32, offset).into())
+                }
+                (VariantType::UDim2, AmbiguousValue::Array4(value)) => Ok(UDim2::new(
+                    UDim::new(
+                        value[0] as f32,
+                        udim_offset(value[1], class_name, prop_name)?,
+                    ),
+                    UDim::new(
+                        value[2] as f32,
+                        udim_offset(value[3], class_name, prop_name)?,
+                    ),
+                )
+                .into()),
+
+                (VariantType::Rect, AmbiguousValue::Array4(value)) => Ok(Rect::new(
+                    Vector2::new(value[0] as f32, value[1] as f32),
+                    Vector2::new(value[2] as f32, value[3] as f32),
+                )
+                .into()),
+
+                (VariantType::NumberRange, AmbiguousValue::Number(value)) => {
+                    Ok(NumberRange::new(value as f32, value as f32).into())
+                }
+                (VariantType::NumberRange, AmbiguousValue::Array2(value)) => {
+                    Ok(NumberRange::new(value[0] as f32, value[1] as f32).into())
+                }
+
+                (VariantType::NumberSequence, AmbiguousValue::Number(value)) => {
+                    Ok(NumberSequence {
+                        keypoints: vec![
+                            NumberSequenceKeypoint::new(0.0, value as f32, 0.0),
+                            NumberSequenceKeypoint::new(1.0, value as f32, 0.0),
+                        ],
+                    }
+                    .into())
+                }
+                (VariantType::NumberSequence, AmbiguousValue::NumberArrays(value)) => {
+                    Ok(number_sequence(value)?.into())
+                }
+
+                (VariantType::ColorSequence, AmbiguousValue::Array3(value)) => {
+                    let color = Color3::new(value[0] as f32, value[1] as f32, value[2] as f32);
+                    Ok(constant_color_sequence(color).into())
+                }
+                (VariantType::ColorSequence, AmbiguousValue::String(value)) => {
+                    let color = Color3::from(parse_hex_color(&value)?);
+                    Ok(constant_color_sequence(color).into())
+                }
+                (VariantType::ColorSequence, AmbiguousValue::NumberArrays(value)) => {
+                    Ok(color_sequence(value)?.into())
+                }
+
+                (VariantType::PhysicalProperties, AmbiguousValue::String(value))
+                    if value == "Default" =>
+                {
+                    Ok(PhysicalProperties::Default.into())
+                }
+                (VariantType::PhysicalProperties, AmbiguousValue::Array5(value)) => {
+                    Ok(PhysicalProperties::Custom(CustomPhysicalProperties {
+                        density: value[0] as f32,
+                        friction: value[1] as f32,
+                        elasticity: value[2] as f32,
+                        friction_weight: value[3] as f32,
+                        elasticity_weight: value[4] as f32,
+                    })
+                    .into())
                 }
 
                 (VariantType::Attributes, AmbiguousValue::Attributes(value)) => Ok(value.into()),
@@ -169,13 +398,43 @@ impl AmbiguousValue {
         }
     }
 
+    /// Resolves this value without knowing what type it should be, like for
+    /// attributes. Only shapes that clearly map to one type are accepted:
+    ///
+    /// - Arrays of two or three numbers become a `Vector2` or `Vector3`
+    /// - Arrays of twelve numbers become a `CFrame`
+    /// - Arrays of keypoints become a `NumberSequence` when each keypoint has
+    ///   two or three numbers, or a `ColorSequence` when each has four
+    ///
+    /// Strings always stay strings.
     pub fn resolve_unambiguous(self) -> anyhow::Result<Variant> {
         match self {
             AmbiguousValue::Bool(value) => Ok(value.into()),
             AmbiguousValue::Number(value) => Ok(value.into()),
             AmbiguousValue::String(value) => Ok(value.into()),
 
-            other => bail!("Cannot unambiguously resolve the value {other:?}"),
+            AmbiguousValue::Array2(value) => {
+                Ok(Vector2::new(value[0] as f32, value[1] as f32).into())
+            }
+            AmbiguousValue::Array3(value) => {
+                Ok(Vector3::new(value[0] as f32, value[1] as f32, value[2] as f32).into())
+            }
+            AmbiguousValue::Array12(value) => Ok(array_to_cframe(value).into()),
+
+            AmbiguousValue::NumberArrays(value) => match value.first().map(Vec::len) {
+                Some(2 | 3) => Ok(number_sequence(value)?.into()),
+                Some(4) => Ok(color_sequence(value)?.into()),
+                _ => bail!(
+                    "Cannot tell what type {} should be. Keypoints should have \
+                     2 or 3 numbers for a NumberSequence, or 4 for a ColorSequence",
+                    AmbiguousValue::NumberArrays(value).describe()
+                ),
+            },
+
+            other => bail!(
+                "Cannot unambiguously resolve the value {other:?}. \
+                 Try specifying its type, like {{\"UDim2\": [[0, 0], [0, 0]]}}"
+            ),
         }
     }
 
@@ -188,7 +447,9 @@ impl AmbiguousValue {
             AmbiguousValue::Array2(_) => "an array of two numbers",
             AmbiguousValue::Array3(_) => "an array of three numbers",
             AmbiguousValue::Array4(_) => "an array of four numbers",
+            AmbiguousValue::Array5(_) => "an array of five numbers",
             AmbiguousValue::Array12(_) => "an array of twelve numbers",
+            AmbiguousValue::NumberArrays(_) => "an array of arrays of numbers",
             AmbiguousValue::Attributes(_) => "an object containing attributes",
             AmbiguousValue::Font(_) => "an object describing a Font",
             AmbiguousValue::MaterialColors(_) => "an object describing MaterialColors",
@@ -196,21 +457,131 @@ impl AmbiguousValue {
     }
 }
 
-fn find_descriptor(
-    class_name: &str,
-    prop_name: &str,
-) -> Option<&'static PropertyDescriptor<'static>> {
-    let database = rbx_reflection_database::get();
-    let mut current_class_name = class_name;
-
-    loop {
-        let class = database.classes.get(current_class_name)?;
-        if let Some(descriptor) = class.properties.get(prop_name) {
-            return Some(descriptor);
-        }
+/// Parses a color written like `#ff8000` or `#f80`.
+fn parse_hex_color(value: &str) -> anyhow::Result<Color3uint8> {
+    let error = || {
+        format_err!(
+            "Invalid color '{}'. Colors can be written as hex strings like #ff8000 or #f80",
+            value
+        )
+    };
+
+    let hex = value.strip_prefix('#').ok_or_else(error)?;
+    let digits = hex
+        .chars()
+        .map(|digit| digit.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(error)?;
+
+    match digits[..] {
+        [r, g, b] => Ok(Color3uint8::new(r * 17, g * 17, b * 17)),
+        [r1, r2, g1, g2, b1, b2] => Ok(Color3uint8::new(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+        _ => Err(error()),
+    }
+}
+
+/// UDim offsets are whole numbers of pixels, so anything that would be
+/// truncated or wrap around when stored is rejected.
+fn udim_offset(value: f64, class_name: &str, prop_name: &str) -> anyhow::Result<i32> {
+    if value.fract() != 0.0 || !(i32::MIN as f64..=i32::MAX as f64).contains(&value) {
+        bail!(
+            "Invalid UDim offset {} for property {}.{}. \
+             Offsets must be whole numbers from {} to {}",
+            value,
+            class_name,
+            prop_name,
+            i32::MIN,
+            i32::MAX
+        );
+    }
 
-        current_class_name = class.superclass.as_deref()?;
+    Ok(value as i32)
+}
+
+fn array_to_cframe(value: [f64; 12]) -> CFrame {
+    let value = value.map(|v| v as f32);
+    let pos = Vector3::new(value[0], value[1], value[2]);
+    let orientation = Matrix3::new(
+        Vector3::new(value[3], value[4], value[5]),
+        Vector3::new(value[6], value[7], value[8]),
+        Vector3::new(value[9], value[10], value[11]),
+    );
+
+    CFrame::new(pos, orientation)
+}
+
+/// Makes sure that keypoints are in order and cover the whole timeline, which
+/// Roblox requires of sequences.
+fn check_keypoint_times(times: &[f64]) -> anyhow::Result<()> {
+    if times.len() < 2 {
+        bail!("Sequences must have at least two keypoints");
+    }
+
+    if times[0] != 0.0 || times[times.len() - 1] != 1.0 {
+        bail!("The first keypoint of a sequence must be at time 0 and the last must be at time 1");
     }
+
+    if times.windows(2).any(|pair| pair[0] > pair[1]) {
+        bail!("Keypoints in a sequence must be sorted by time");
+    }
+
+    Ok(())
+}
+
+/// Builds a `NumberSequence` from keypoints written as `[time, value]` or
+/// `[time, value, envelope]`.
+fn number_sequence(keypoints: Vec<Vec<f64>>) -> anyhow::Result<NumberSequence> {
+    let keypoints = keypoints
+        .into_iter()
+        .map(|keypoint| match keypoint[..] {
+            [time, value] => Ok(NumberSequenceKeypoint::new(time as f32, value as f32, 0.0)),
+            [time, value, envelope] => Ok(NumberSequenceKeypoint::new(
+                time as f32,
+                value as f32,
+                envelope as f32,
+            )),
+            _ => bail!(
+                "NumberSequence keypoints must be [time, value] or [time, value, envelope], got {:?}",
+                keypoint
+            ),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let times: Vec<_> = keypoints.iter().map(|k| k.time as f64).collect();
+    check_keypoint_times(&times)?;
+
+    Ok(NumberSequence { keypoints })
+}
+
+fn constant_color_sequence(color: Color3) -> ColorSequence {
+    ColorSequence {
+        keypoints: vec![
+            ColorSequenceKeypoint::new(0.0, color),
+            ColorSequenceKeypoint::new(1.0, color),
+        ],
+    }
+}
+
+/// Builds a `ColorSequence` from keypoints written as `[time, r, g, b]`.
+fn color_sequence(keypoints: Vec<Vec<f64>>) -> anyhow::Result<ColorSequence> {
+    let keypoints = keypoints
+        .into_iter()
+        .map(|keypoint| match keypoint[..] {
+            [time, r, g, b] => Ok(ColorSequenceKeypoint::new(
+                time as f32,
+                Color3::new(r as f32, g as f32, b as f32),
+            )),
+            _ => bail!(
+                "ColorSequence keypoints must be [time, r, g, b], got {:?}",
+                keypoint
+            ),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let times: Vec<_> = keypoints.iter().map(|k| k.time as f64).collect();
+    check_keypoint_times(&times)?;
+
+    Ok(ColorSequence { keypoints })
 }
 
 /// Outputs a string containing up to MAX_ITEMS entries from the given list. If
@@ -335,6 +706,188 @@ mod test {
 
         // There aren't any user-facing Color3uint8 properties. If there are
         // some, we should treat them the same in the future.
+
+        assert_eq!(
+            resolve("Lighting", "Ambient", "\"#ff8000\""),
+            Variant::Color3(Color3::new(1.0, 128.0 / 255.0, 0.0)),
+        );
+        assert_eq!(
+            resolve("Part", "Color", "\"#f80\""),
+            Variant::Color3(Color3::new(1.0, 136.0 / 255.0, 0.0)),
+        );
+
+        assert_eq!(
+            resolve("Part", "BrickColor", "\"Really red\""),
+            Variant::BrickColor(BrickColor::ReallyRed),
+        );
+        assert_eq!(
+            resolve("Part", "BrickColor", "1004"),
+            Variant::BrickColor(BrickColor::ReallyRed),
+        );
+
+        for invalid in ["-1", "1004.5", "66540"] {
+            let unresolved: UnresolvedValue = serde_json::from_str(invalid).unwrap();
+            let error = unresolved.resolve("Part", "BrickColor").unwrap_err();
+
+            assert!(
+                error.to_string().contains("Part.BrickColor"),
+                "unexpected error for {}: {}",
+                invalid,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn udims() {
+        assert_eq!(
+            resolve("UIPadding", "PaddingLeft", "[0.5, 10]"),
+            Variant::UDim(UDim::new(0.5, 10)),
+        );
+
+        assert_eq!(
+            resolve("Frame", "Size", "[1, 0, 0.5, 20]"),
+            Variant::UDim2(UDim2::new(UDim::new(1.0, 0), UDim::new(0.5, 20))),
+        );
+
+        for (class_name, prop_name, invalid) in [
+            ("UIPadding", "PaddingLeft", "[0.5, 10.5]"),
+            ("UIPadding", "PaddingLeft", "[0, 3000000000]"),
+            ("Frame", "Size", "[1, 0, 0.5, 20.25]"),
+            ("Frame", "Size", "[1, -3000000000, 0, 0]"),
+        ] {
+            let unresolved: UnresolvedValue = serde_json::from_str(invalid).unwrap();
+            let error = unresolved.resolve(class_name, prop_name).unwrap_err();
+
+            assert!(
+                error
+                    .to_string()
+                    .contains(&format!("{}.{}", class_name, prop_name)),
+                "unexpected error for {}: {}",
+                invalid,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(
+            resolve("ParticleEmitter", "Lifetime", "[1, 2]"),
+            Variant::NumberRange(NumberRange::new(1.0, 2.0)),
+        );
+        assert_eq!(
+            resolve("ParticleEmitter", "Lifetime", "5"),
+            Variant::NumberRange(NumberRange::new(5.0, 5.0)),
+        );
+
+        assert_eq!(
+            resolve("ImageLabel", "SliceCenter", "[1, 2, 3, 4]"),
+            Variant::Rect(Rect::new(Vector2::new(1.0, 2.0), Vector2::new(3.0, 4.0))),
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(
+            resolve(
+                "ParticleEmitter",
+                "Transparency",
+                "[[0, 1], [0.5, 0, 0.25], [1, 1]]"
+            ),
+            Variant::NumberSequence(NumberSequence {
+                keypoints: vec![
+                    NumberSequenceKeypoint::new(0.0, 1.0, 0.0),
+                    NumberSequenceKeypoint::new(0.5, 0.0, 0.25),
+                    NumberSequenceKeypoint::new(1.0, 1.0, 0.0),
+                ],
+            }),
+        );
+        assert_eq!(
+            resolve("ParticleEmitter", "Transparency", "0.5"),
+            Variant::NumberSequence(NumberSequence {
+                keypoints: vec![
+                    NumberSequenceKeypoint::new(0.0, 0.5, 0.0),
+                    NumberSequenceKeypoint::new(1.0, 0.5, 0.0),
+                ],
+            }),
+        );
+
+        let red = Color3::new(1.0, 0.0, 0.0);
+        let blue = Color3::new(0.0, 0.0, 1.0);
+        assert_eq!(
+            resolve("ParticleEmitter", "Color", "[[0, 1, 0, 0], [1, 0, 0, 1]]"),
+            Variant::ColorSequence(ColorSequence {
+                keypoints: vec![
+                    ColorSequenceKeypoint::new(0.0, red),
+                    ColorSequenceKeypoint::new(1.0, blue),
+                ],
+            }),
+        );
+        assert_eq!(
+            resolve("ParticleEmitter", "Color", "\"#ff0000\""),
+            Variant::ColorSequence(ColorSequence {
+                keypoints: vec![
+                    ColorSequenceKeypoint::new(0.0, red),
+                    ColorSequenceKeypoint::new(1.0, red),
+                ],
+            }),
+        );
+
+        // Keypoints have to span the whole sequence
+        let unresolved: UnresolvedValue = serde_json::from_str("[[0, 1], [0.5, 0]]").unwrap();
+        assert!(unresolved
+            .resolve("ParticleEmitter", "Transparency")
+            .is_err());
+    }
+
+    #[test]
+    fn physical_properties() {
+        assert_eq!(
+            resolve("Part", "CustomPhysicalProperties", "\"Default\""),
+            Variant::PhysicalProperties(PhysicalProperties::Default),
+        );
+        assert_eq!(
+            resolve("Part", "CustomPhysicalProperties", "[0.7, 0.3, 0.5, 1, 1]"),
+            Variant::PhysicalProperties(PhysicalProperties::Custom(CustomPhysicalProperties {
+                density: 0.7,
+                friction: 0.3,
+                elasticity: 0.5,
+                friction_weight: 1.0,
+                elasticity_weight: 1.0,
+            })),
+        );
+    }
+
+    #[test]
+    fn inferred_types() {
+        assert_eq!(
+            resolve_unambiguous("[1, 2]"),
+            Variant::Vector2(Vector2::new(1.0, 2.0)),
+        );
+        assert_eq!(
+            resolve_unambiguous("[1, 2, 3]"),
+            Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)),
+        );
+        assert_eq!(
+            resolve_unambiguous("[[0, 1], [1, 0]]"),
+            Variant::NumberSequence(NumberSequence {
+                keypoints: vec![
+                    NumberSequenceKeypoint::new(0.0, 1.0, 0.0),
+                    NumberSequenceKeypoint::new(1.0, 0.0, 0.0),
+                ],
+            }),
+        );
+
+        // Hex strings are still strings when there's no type to go by
+        assert_eq!(
+            resolve_unambiguous("\"#ff0000\""),
+            Variant::String("#ff0000".into()),
+        );
+
+        // Four numbers could be a UDim2 or a Rect
+        let unresolved: UnresolvedValue = serde_json::from_str("[1, 2, 3, 4]").unwrap();
+        assert!(unresolved.resolve_unambiguous().is_err());
     }
 
     #[test]
@@ -386,4 +939,83 @@ mod test {
             Variant::MaterialColors(material_colors)
         )
     }
+
+    #[test]
+    fn files() {
+        use memofs::{InMemoryFs, VfsSnapshot};
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/root",
+            VfsSnapshot::dir([
+                ("text.txt", VfsSnapshot::file("Hello, world!")),
+                ("technology.txt", VfsSnapshot::file("Voxel")),
+                ("data.bin", VfsSnapshot::file(vec![0, 1, 2, 255])),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let load = |json_value: &str| {
+            let mut unresolved: UnresolvedValue = serde_json::from_str(json_value).unwrap();
+            let path = unresolved.load_file(&vfs, Path::new("/root")).unwrap();
+            (unresolved, path)
+        };
+
+        let (unresolved, path) = load(r#"{"$file": "text.txt"}"#);
+        assert_eq!(path, Some(PathBuf::from("/root/text.txt")));
+        assert_eq!(
+            unresolved.resolve("StringValue", "Value").unwrap(),
+            Variant::String("Hello, world!".into()),
+        );
+
+        // Text files resolve the same way that strings do
+        let (unresolved, _) = load(r#"{"$file": "technology.txt"}"#);
+        assert_eq!(
+            unresolved.resolve("Lighting", "Technology").unwrap(),
+            Variant::Enum(Enum::from_u32(1)),
+        );
+
+        let (unresolved, path) = load(r#"{"$binaryFile": "data.bin"}"#);
+        assert_eq!(path, Some(PathBuf::from("/root/data.bin")));
+        assert_eq!(
+            unresolved.resolve_unambiguous().unwrap(),
+            Variant::BinaryString(vec![0, 1, 2, 255].into()),
+        );
+
+        // Values that aren't files are left alone
+        let (unresolved, path) = load("\"text.txt\"");
+        assert_eq!(path, None);
+        assert_eq!(
+            unresolved,
+            UnresolvedValue::Ambiguous(AmbiguousValue::String("text.txt".into())),
+        );
+    }
+
+    #[test]
+    fn ref_paths() {
+        let ref_path = |class: &str, prop: &str, json_value: &str| {
+            let unresolved: UnresolvedValue = serde_json::from_str(json_value).unwrap();
+            unresolved.ref_path(class, prop)
+        };
+
+        assert_eq!(
+            ref_path("Model", "PrimaryPart", r#""./Handle""#).unwrap(),
+            Some("./Handle".parse().unwrap()),
+        );
+        assert_eq!(
+            ref_path("ObjectValue", "Value", r#"{"Ref": "@game/Workspace"}"#).unwrap(),
+            Some("@game/Workspace".parse().unwrap()),
+        );
+
+        // Strings are only paths for Ref properties
+        assert_eq!(
+            ref_path("StringValue", "Value", r#""./Handle""#).unwrap(),
+            None
+        );
+
+        assert!(ref_path("Model", "PrimaryPart", r#""Handle""#).is_err());
+        assert!(ref_path("StringValue", "Value", r#"{"Ref": "./Handle"}"#).is_err());
+    }
 }
@@ -1,6 +1,7 @@
-use std::{fmt, sync::Arc};
+use std::{fmt, str::FromStr, sync::Arc};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 pub const REF_ID_ATTRIBUTE_NAME: &str = "Rojo_Id";
 pub const REF_POINTER_ATTRIBUTE_PREFIX: &str = "Rojo_Target_";
@@ -28,3 +29,142 @@ impl fmt::Display for RojoRef {
         write!(f, "{}", self.as_str())
     }
 }
+
+/// A reference to another instance by its location in the tree, relative to
+/// either the instance holding the reference or the root of the tree.
+///
+/// Paths are written like `./Handle`, `../Sibling/Child`, or
+/// `@game/Workspace/Spawn`, where `@game` is the root of the tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RefPath {
+    absolute: bool,
+    components: Vec<RefPathComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RefPathComponent {
+    Parent,
+    Child(String),
+}
+
+impl RefPath {
+    /// Whether this path starts at the root of the tree instead of the
+    /// instance holding the reference.
+    pub fn is_absolute(&self) -> bool {
+        self.absolute
+    }
+
+    pub fn components(&self) -> &[RefPathComponent] {
+        &self.components
+    }
+}
+
+impl FromStr for RefPath {
+    type Err = RefPathError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut segments = source.split('/');
+
+        let (absolute, mut components) = match segments.next() {
+            Some("@game") => (true, Vec::new()),
+            Some(".") => (false, Vec::new()),
+            Some("..") => (false, vec![RefPathComponent::Parent]),
+            _ => return Err(RefPathError::BadStart(source.to_owned())),
+        };
+
+        for segment in segments {
+            match segment {
+                "" => return Err(RefPathError::EmptyName(source.to_owned())),
+                "." => {}
+                ".." => components.push(RefPathComponent::Parent),
+                name => components.push(RefPathComponent::Child(name.to_owned())),
+            }
+        }
+
+        Ok(Self {
+            absolute,
+            components,
+        })
+    }
+}
+
+impl TryFrom<String> for RefPath {
+    type Error = RefPathError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RefPath> for String {
+    fn from(value: RefPath) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for RefPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut components = self.components.as_slice();
+
+        if self.absolute {
+            f.write_str("@game")?;
+        } else if let [RefPathComponent::Parent, rest @ ..] = components {
+            f.write_str("..")?;
+            components = rest;
+        } else {
+            f.write_str(".")?;
+        }
+
+        for component in components {
+            match component {
+                RefPathComponent::Parent => f.write_str("/..")?,
+                RefPathComponent::Child(name) => write!(f, "/{}", name)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RefPathError {
+    #[error("Ref path '{0}' must start with './', '../', or '@game/'")]
+    BadStart(String),
+
+    #[error("Ref path '{0}' contains an empty instance name")]
+    EmptyName(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_ref_paths() {
+        let path: RefPath = "./Handle".parse().unwrap();
+        assert!(!path.is_absolute());
+        assert_eq!(
+            path.components(),
+            [RefPathComponent::Child("Handle".into())]
+        );
+
+        let path: RefPath = "../Sibling/./Child".parse().unwrap();
+        assert_eq!(
+            path.components(),
+            [
+                RefPathComponent::Parent,
+                RefPathComponent::Child("Sibling".into()),
+                RefPathComponent::Child("Child".into()),
+            ]
+        );
+        assert_eq!(path.to_string(), "../Sibling/Child");
+
+        let path: RefPath = "@game/Workspace/Spawn".parse().unwrap();
+        assert!(path.is_absolute());
+        assert_eq!(path.to_string(), "@game/Workspace/Spawn");
+
+        assert!("Handle".parse::<RefPath>().is_err());
+        assert!("./Model//Handle".parse::<RefPath>().is_err());
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     collections::HashSet,
     io,
     net::IpAddr,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex, MutexGuard},
     time::Instant,
 };
@@ -16,6 +16,7 @@ use crate::{
     change_processor::ChangeProcessor,
     message_queue::MessageQueue,
     project::{Project, ProjectError},
+    reflection,
     session_id::SessionId,
     snapshot::{
         apply_patch_set, compute_patch_set, AppliedPatchSet, InstanceContext, InstanceSnapshot,
@@ -88,6 +89,19 @@ pub struct ServeSession {
     tree_mutation_sender: Sender<PatchSet>,
 }
 
+/// Options for a serve session that are given on the command line instead of
+/// in the project.
+#[derive(Debug, Default, Clone)]
+pub struct ServeSessionOptions {
+    /// The name of one of the root project's `buildProfiles`, whose transforms
+    /// are applied to every script.
+    pub build_profile: Option<String>,
+
+    /// Whether sync rules that use the `command` middleware may run their
+    /// commands, which is only allowed with `--allow-commands`.
+    pub allow_commands: bool,
+}
+
 impl ServeSession {
     /// Start a new serve session from the given in-memory filesystem and start
     /// path.
@@ -96,6 +110,16 @@ impl ServeSession {
     /// currently loaded from the filesystem directly instead of through the
     /// in-memory filesystem layer.
     pub fn new<P: AsRef<Path>>(vfs: Vfs, start_path: P) -> Result<Self, ServeSessionError> {
+        Self::with_options(vfs, start_path, &ServeSessionOptions::default())
+    }
+
+    /// Start a new serve session like `new`, with options that don't come
+    /// from the project.
+    pub fn with_options<P: AsRef<Path>>(
+        vfs: Vfs,
+        start_path: P,
+        options: &ServeSessionOptions,
+    ) -> Result<Self, ServeSessionError> {
         let start_path = start_path.as_ref();
         let start_time = Instant::now();
 
@@ -111,22 +135,66 @@ impl ServeSession {
 
         let root_project = Project::load_exact(&vfs, &project_path, None)?;
 
+        match &root_project.reflection_database {
+            Some(_) if reflection::has_command_line_database() => {
+                log::debug!(
+                    "Ignoring reflectionDatabase from project because one was given on the command line"
+                );
+            }
+            Some(database_path) => {
+                let database_path = root_project.folder_location().join(database_path);
+                let contents = vfs.read(&database_path)?;
+                reflection::load_project_database(&database_path, &contents)?;
+            }
+            None => reflection::clear_project_database(),
+        }
+
         let mut tree = RojoTree::new(InstanceSnapshot::new());
 
         let root_id = tree.get_root_id();
 
-        let instance_context =
+        let mut instance_context =
             InstanceContext::with_emit_legacy_scripts(root_project.emit_legacy_scripts);
+        instance_context.set_allow_commands(options.allow_commands);
+
+        if let Some(profile_name) = &options.build_profile {
+            let transforms = root_project
+                .build_profiles
+                .get(profile_name)
+                .ok_or_else(|| ServeSessionError::UnknownBuildProfile {
+                    name: profile_name.clone(),
+                    project_path: project_path.to_path_buf(),
+                })?;
+
+            instance_context.set_luau_transforms(transforms.clone());
+        }
 
         log::trace!("Generating snapshot of instances from VFS");
         let snapshot = snapshot_from_vfs(&instance_context, &vfs, start_path)?;
 
+        if let (Some(profile_name), Some(transforms)) =
+            (&options.build_profile, &instance_context.luau_transforms)
+        {
+            for name in transforms.undeclared_defines(&instance_context.declared_constants) {
+                log::warn!(
+                    "Build profile '{}' defines {}, but no script declares it with --!rojo-define",
+                    profile_name,
+                    name
+                );
+            }
+        }
+
         log::trace!("Computing initial patch set");
         let patch_set = compute_patch_set(snapshot, &tree, root_id);
 
         log::trace!("Applying initial patch set");
         apply_patch_set(&mut tree, patch_set);
 
+        let errors = tree.take_errors();
+        if !errors.is_empty() {
+            return Err(ServeSessionError::InvalidTree { errors });
+        }
+
         let session_id = SessionId::new();
         let message_queue = MessageQueue::new();
 
@@ -219,6 +287,12 @@ impl ServeSession {
 
 #[derive(Debug, Error)]
 pub enum ServeSessionError {
+    #[error("Build profile '{name}' is not defined in the buildProfiles of {}", .project_path.display())]
+    UnknownBuildProfile { name: String, project_path: PathBuf },
+
+    #[error("{}", .errors.join("\n"))]
+    InvalidTree { errors: Vec<String> },
+
     #[error(transparent)]
     Io {
         #[from]
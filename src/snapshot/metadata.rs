@@ -1,28 +1,33 @@
 use std::{
+    collections::BTreeMap,
     fmt,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 use anyhow::Context;
+use rbx_dom_weak::types::Variant;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    asset_manifest::{resolve_asset, AssetManifest},
     glob::Glob,
+    luau_transform::{DeclaredConstants, LuauTransforms},
     path_serializer,
     project::ProjectNode,
-    snapshot_middleware::{emit_legacy_scripts_default, Middleware},
-    RojoRef,
+    resolution::UnresolvedValue,
+    snapshot_middleware::{emit_legacy_scripts_default, CommandOutputCache, Middleware},
+    RefPath, RojoRef,
 };
 
 /// Rojo-specific metadata that can be associated with an instance or a snapshot
 /// of an instance.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InstanceMetadata {
-    /// Whether instances not present in the source should be ignored when
+    /// Which instances not present in the source should be ignored when
     /// live-syncing. This is useful when there are instances that Rojo does not
     /// manage.
-    pub ignore_unknown_instances: bool,
+    pub ignore_unknown_instances: IgnoreUnknownInstances,
 
     /// If a change occurs to this instance, the instigating source is what
     /// should be run through the snapshot functions to regenerate it.
@@ -62,20 +67,38 @@ pub struct InstanceMetadata {
 
     /// Indicates the ID used for Ref properties pointing to this Instance.
     pub specified_id: Option<RojoRef>,
+
+    /// Ref properties on this instance that point to other instances by
+    /// their path in the tree. These are resolved when patches are computed
+    /// and applied, since the target might not exist yet.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ref_paths: BTreeMap<String, RefPath>,
+
+    /// The name given to this instance by whatever snapshotted it, like a
+    /// `$path` glob naming matches after their folders, instead of the name
+    /// that its instigating source would give it. This name is kept when the
+    /// instigating source is snapshotted again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_override: Option<String>,
 }
 
 impl InstanceMetadata {
     pub fn new() -> Self {
         Self {
-            ignore_unknown_instances: false,
+            ignore_unknown_instances: IgnoreUnknownInstances::Bool(false),
             instigating_source: None,
             relevant_paths: Vec::new(),
             context: InstanceContext::default(),
             specified_id: None,
+            ref_paths: BTreeMap::new(),
+            name_override: None,
         }
     }
 
-    pub fn ignore_unknown_instances(self, ignore_unknown_instances: bool) -> Self {
+    pub fn ignore_unknown_instances(
+        self,
+        ignore_unknown_instances: IgnoreUnknownInstances,
+    ) -> Self {
         Self {
             ignore_unknown_instances,
             ..self
@@ -117,13 +140,124 @@ impl Default for InstanceMetadata {
     }
 }
 
+/// Decides which children of an instance are left alone when they exist in
+/// Roblox Studio but not in Rojo's tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IgnoreUnknownInstances {
+    /// Either every unknown child is left alone or none of them are.
+    Bool(bool),
+
+    /// Only unknown children matching one of these patterns are left alone.
+    Patterns(Vec<UnknownInstancePattern>),
+}
+
+impl From<bool> for IgnoreUnknownInstances {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl fmt::Display for IgnoreUnknownInstances {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Patterns(patterns) => {
+                let patterns: Vec<_> = patterns.iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", patterns.join(", "))
+            }
+        }
+    }
+}
+
+/// Matches unknown instances by name, like `Terrain`, or by class, like
+/// `class:Camera`. Class patterns also match subclasses of the class.
+/// Names that start with `class:` can be written as `name:class:...`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UnknownInstancePattern {
+    Name(String),
+    Class(String),
+}
+
+impl TryFrom<String> for UnknownInstancePattern {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let pattern = if let Some(class_name) = value.strip_prefix("class:") {
+            Self::Class(class_name.to_owned())
+        } else if let Some(name) = value.strip_prefix("name:") {
+            Self::Name(name.to_owned())
+        } else {
+            Self::Name(value.clone())
+        };
+
+        match &pattern {
+            Self::Name(name) | Self::Class(name) if name.is_empty() => {
+                anyhow::bail!("Unknown instance pattern '{}' is missing a name", value)
+            }
+            _ => Ok(pattern),
+        }
+    }
+}
+
+impl From<UnknownInstancePattern> for String {
+    fn from(value: UnknownInstancePattern) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for UnknownInstancePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) if name.starts_with("class:") || name.starts_with("name:") => {
+                write!(f, "name:{}", name)
+            }
+            Self::Name(name) => f.write_str(name),
+            Self::Class(class_name) => write!(f, "class:{}", class_name),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InstanceContext {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub path_ignore_rules: Arc<Vec<PathIgnoreRule>>,
     pub emit_legacy_scripts: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// Whether escapes like `%2F` in file names are decoded into the names
+    /// of instances.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub escaped_file_names: bool,
+    /// Whether the `command` middleware may run the commands given by sync
+    /// rules, which Rojo only allows when run with `--allow-commands`.
+    #[serde(skip)]
+    pub allow_commands: bool,
+    /// Outputs of commands run by the `command` middleware, which is shared
+    /// by every instance in a serve session.
+    #[serde(skip)]
+    pub command_output_cache: Arc<CommandOutputCache>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub sync_rules: Vec<SyncRule>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub init_file_rules: Vec<InitFileRule>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub class_defaults: Arc<BTreeMap<String, BTreeMap<String, Variant>>>,
+    #[serde(default, skip_serializing_if = "DataModuleOptions::is_empty")]
+    pub data_modules: DataModuleOptions,
+    #[serde(skip)]
+    pub asset_manifest: Option<Arc<AssetManifest>>,
+    #[serde(skip)]
+    pub luau_transforms: Option<Arc<LuauTransforms>>,
+    /// Constants declared by scripts while applying `luau_transforms`, which
+    /// is shared by every instance in a serve session.
+    #[serde(skip)]
+    pub declared_constants: Arc<DeclaredConstants>,
+    /// The file being snapshotted and the sync rule that picked its
+    /// middleware, when that rule sets a class name or properties.
+    #[serde(skip)]
+    pub pending_sync_rule: Option<(PathBuf, Arc<SyncRule>)>,
 }
 
 impl InstanceContext {
@@ -131,7 +265,18 @@ impl InstanceContext {
         Self {
             path_ignore_rules: Arc::new(Vec::new()),
             emit_legacy_scripts: emit_legacy_scripts_default().unwrap(),
+            strict: false,
+            escaped_file_names: false,
+            allow_commands: false,
+            command_output_cache: Arc::new(CommandOutputCache::default()),
             sync_rules: Vec::new(),
+            init_file_rules: Vec::new(),
+            class_defaults: Arc::new(BTreeMap::new()),
+            data_modules: DataModuleOptions::default(),
+            asset_manifest: None,
+            luau_transforms: None,
+            declared_constants: Arc::new(DeclaredConstants::default()),
+            pending_sync_rule: None,
         }
     }
 
@@ -175,10 +320,86 @@ impl InstanceContext {
         self.sync_rules.clear();
     }
 
+    /// Extend the list of init file rules in the context with the given new
+    /// rules.
+    pub fn add_init_file_rules<I>(&mut self, new_rules: I)
+    where
+        I: IntoIterator<Item = InitFileRule>,
+    {
+        self.init_file_rules.extend(new_rules);
+    }
+
+    /// Clears all init file rules for this InstanceContext
+    pub fn clear_init_file_rules(&mut self) {
+        self.init_file_rules.clear();
+    }
+
+    /// Replaces the default properties applied to instances of each class.
+    pub fn set_class_defaults(
+        &mut self,
+        class_defaults: BTreeMap<String, BTreeMap<String, Variant>>,
+    ) {
+        self.class_defaults = Arc::new(class_defaults);
+    }
+
     pub fn set_emit_legacy_scripts(&mut self, emit_legacy_scripts: bool) {
         self.emit_legacy_scripts = emit_legacy_scripts;
     }
 
+    /// Sets whether the `command` middleware may run commands.
+    pub fn set_allow_commands(&mut self, allow_commands: bool) {
+        self.allow_commands = allow_commands;
+    }
+
+    /// Sets whether snapshots should be checked against the reflection
+    /// database, failing on any unknown classes or properties.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Sets whether escapes in file names are decoded into instance names.
+    pub fn set_escaped_file_names(&mut self, escaped_file_names: bool) {
+        self.escaped_file_names = escaped_file_names;
+    }
+
+    /// Changes how ModuleScripts are generated from data files. Options that
+    /// aren't set in `data_modules` keep their current values.
+    pub fn merge_data_modules(&mut self, data_modules: DataModuleOptions) {
+        self.data_modules = self.data_modules.merge(data_modules);
+    }
+
+    /// Sets the manifest used to resolve `@asset/` references.
+    pub fn set_asset_manifest(&mut self, asset_manifest: AssetManifest) {
+        self.asset_manifest = Some(Arc::new(asset_manifest));
+    }
+
+    /// Sets the transforms applied to the source of every script, which come
+    /// from the build profile being used.
+    pub fn set_luau_transforms(&mut self, luau_transforms: LuauTransforms) {
+        self.luau_transforms = Some(Arc::new(luau_transforms));
+    }
+
+    /// Marks the sync rule that picked the middleware for the file at `path`
+    /// as still needing to be applied to the file's snapshot.
+    pub fn set_pending_sync_rule(&mut self, path: &Path, rule: &SyncRule) {
+        self.pending_sync_rule = Some((path.to_path_buf(), Arc::new(rule.clone())));
+    }
+
+    /// Returns the sync rule that still needs to be applied to the snapshot
+    /// of the file at `path`, if there is one.
+    pub fn pending_sync_rule(&self, path: &Path) -> Option<&SyncRule> {
+        match &self.pending_sync_rule {
+            Some((rule_path, rule)) if rule_path == path => Some(rule),
+            _ => None,
+        }
+    }
+
+    /// If `value` is an `@asset/` reference, returns the content URL of the
+    /// asset it refers to using this context's asset manifest.
+    pub fn resolve_asset(&self, value: &str) -> anyhow::Result<Option<String>> {
+        resolve_asset(self.asset_manifest.as_deref(), value)
+    }
+
     /// Returns the middleware specified by the first sync rule that
     /// matches the provided path. This does not handle default syncing rules.
     pub fn get_user_sync_rule(&self, path: &Path) -> Option<&SyncRule> {
@@ -192,6 +413,43 @@ impl Default for InstanceContext {
     }
 }
 
+/// Options for the ModuleScripts that Rojo generates from data files, like
+/// JSON, TOML, and YAML files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct DataModuleOptions {
+    /// Whether to export a Luau type inferred from the shape of the data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_type: Option<bool>,
+
+    /// Whether to freeze the data, including any nested tables, with
+    /// `table.freeze`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze: Option<bool>,
+}
+
+impl DataModuleOptions {
+    /// Returns these options with any options set in `other` taking priority.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            export_type: other.export_type.or(self.export_type),
+            freeze: other.freeze.or(self.freeze),
+        }
+    }
+
+    pub fn export_type(&self) -> bool {
+        self.export_type.unwrap_or(false)
+    }
+
+    pub fn freeze(&self) -> bool {
+        self.freeze.unwrap_or(false)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.export_type.is_none() && self.freeze.is_none()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PathIgnoreRule {
     /// The path that this glob is relative to. Since ignore globs are defined
@@ -271,6 +529,18 @@ pub struct SyncRule {
     /// If not specified, the file extension is the only thing cut off.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suffix: Option<String>,
+    /// A class to give instances made by this rule, replacing the class
+    /// chosen by the middleware.
+    #[serde(rename = "className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+    /// Properties to set on instances made by this rule. These are applied
+    /// on top of the middleware's output, and meta files can override them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, UnresolvedValue>,
+    /// The program and arguments to run when using the `command` middleware.
+    /// Files are given to it on stdin, and it should write Luau to stdout.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
     /// The 'base' of the glob above, allowing it to be used
     /// relative to a path instead of absolute.
     #[serde(skip)]
@@ -293,24 +563,68 @@ impl SyncRule {
         }
     }
 
+    /// Returns the part of the name of the file at `path` that the instance
+    /// it turns into is named after.
     pub fn file_name_for_path<'a>(&self, path: &'a Path) -> anyhow::Result<&'a str> {
-        if let Some(suffix) = &self.suffix {
+        let file_name = if let Some(suffix) = &self.suffix {
             let file_name = path
                 .file_name()
                 .and_then(|s| s.to_str())
                 .with_context(|| format!("file name of {} is invalid", path.display()))?;
             if file_name.ends_with(suffix) {
                 let end = file_name.len().saturating_sub(suffix.len());
-                Ok(&file_name[..end])
+                &file_name[..end]
             } else {
-                Ok(file_name)
+                file_name
             }
         } else {
             // If the user doesn't specify a suffix, we assume they just want
             // the name of the file (the file_stem)
             path.file_stem()
                 .and_then(|s| s.to_str())
-                .with_context(|| format!("file name of {} is invalid", path.display()))
-        }
+                .with_context(|| format!("file name of {} is invalid", path.display()))?
+        };
+
+        Ok(file_name)
+    }
+}
+
+/// Represents an user-specified rule for treating files with a given name as
+/// the 'init' file of the directory containing them, similarly to `init.luau`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct InitFileRule {
+    /// The exact file name that this rule applies to, like `index.luau`.
+    pub name: String,
+    /// The middleware used to turn the init file and its directory into an
+    /// Instance.
+    #[serde(rename = "use")]
+    pub middleware: Middleware,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ignore_unknown_instances_patterns() {
+        let ignore: IgnoreUnknownInstances = serde_json::from_str("true").unwrap();
+        assert_eq!(ignore, IgnoreUnknownInstances::Bool(true));
+
+        let ignore: IgnoreUnknownInstances =
+            serde_json::from_str(r#"["Terrain", "class:Camera", "name:class:Odd"]"#).unwrap();
+        assert_eq!(
+            ignore,
+            IgnoreUnknownInstances::Patterns(vec![
+                UnknownInstancePattern::Name("Terrain".into()),
+                UnknownInstancePattern::Class("Camera".into()),
+                UnknownInstancePattern::Name("class:Odd".into()),
+            ])
+        );
+        assert_eq!(
+            serde_json::to_string(&ignore).unwrap(),
+            r#"["Terrain","class:Camera","name:class:Odd"]"#
+        );
+
+        assert!(serde_json::from_str::<IgnoreUnknownInstances>(r#"["class:"]"#).is_err());
     }
 }
@@ -11,7 +11,9 @@ use super::{
     patch::{AppliedPatchSet, AppliedPatchUpdate, PatchSet, PatchUpdate},
     InstanceSnapshot, RojoTree,
 };
-use crate::{multimap::MultiMap, RojoRef, REF_ID_ATTRIBUTE_NAME, REF_POINTER_ATTRIBUTE_PREFIX};
+use crate::{
+    multimap::MultiMap, RefPath, RojoRef, REF_ID_ATTRIBUTE_NAME, REF_POINTER_ATTRIBUTE_PREFIX,
+};
 
 /// Consumes the input `PatchSet`, applying all of its prescribed changes to the
 /// tree and returns an `AppliedPatchSet`, which can be used to keep another
@@ -78,6 +80,11 @@ struct PatchApplyContext {
     /// properties.
     attribute_refs_to_rewrite: MultiMap<Ref, (String, String)>,
 
+    /// Tracks all ref properties that were specified using paths. Like
+    /// attribute refs, these are resolved once everything else is done so
+    /// that they can point to instances added by this patch.
+    path_refs_to_rewrite: MultiMap<Ref, (String, RefPath)>,
+
     /// The current applied patch result, describing changes made to the tree.
     applied_patch_set: AppliedPatchSet,
 }
@@ -116,8 +123,18 @@ fn finalize_patch_application(context: PatchApplyContext, tree: &mut RojoTree) -
     let mut real_rewrites = Vec::new();
     for (id, map) in context.attribute_refs_to_rewrite {
         for (prop_name, prop_value) in map {
-            if let Some(target) = tree.get_specified_id(&RojoRef::new(prop_value)) {
+            let specified_id = RojoRef::new(prop_value);
+
+            if let Some(target) = tree.get_specified_id(&specified_id) {
                 real_rewrites.push((prop_name, Variant::Ref(target)))
+            } else if tree.is_specified_id_ambiguous(&specified_id) {
+                let message = format!(
+                    "Could not set Ref property {} of {}: the ID '{}' is used by more than one instance",
+                    prop_name,
+                    tree.describe_source(id),
+                    specified_id
+                );
+                tree.report_error(message);
             }
         }
         let mut instance = tree
@@ -126,6 +143,27 @@ fn finalize_patch_application(context: PatchApplyContext, tree: &mut RojoTree) -
         instance.properties_mut().extend(real_rewrites.drain(..));
     }
 
+    for (id, map) in context.path_refs_to_rewrite {
+        for (prop_name, path) in map {
+            match tree.resolve_ref_path(id, &path) {
+                Ok(target) => real_rewrites.push((prop_name, Variant::Ref(target))),
+                Err(err) => {
+                    let message = format!(
+                        "Could not set Ref property {} of {}: {}",
+                        prop_name,
+                        tree.describe_source(id),
+                        err
+                    );
+                    tree.report_error(message);
+                }
+            }
+        }
+        let mut instance = tree
+            .get_instance_mut(id)
+            .expect("Invalid instance ID in deferred path ref map");
+        instance.properties_mut().extend(real_rewrites.drain(..));
+    }
+
     context.applied_patch_set
 }
 
@@ -241,12 +279,19 @@ fn apply_update_child(context: &mut PatchApplyContext, tree: &mut RojoTree, patc
 /// `PatchApplyContext` to be rewritten at the end of the patch application
 /// process.
 ///
-/// Currently, this only uses attributes but it can easily handle rewriting
-/// referents in other ways too!
+/// Refs can be specified using attributes or using paths stored in the
+/// instance's metadata.
 fn defer_ref_properties(tree: &mut RojoTree, id: Ref, context: &mut PatchApplyContext) {
     let instance = tree
         .get_instance(id)
         .expect("Instances should exist when calculating deferred refs");
+
+    for (prop_name, path) in &instance.metadata().ref_paths {
+        context
+            .path_refs_to_rewrite
+            .insert(id, (prop_name.clone(), path.clone()));
+    }
+
     let attributes = match instance.properties().get("Attributes") {
         Some(Variant::Attributes(attrs)) => attrs,
         _ => return,
@@ -102,7 +102,7 @@ fn compute_property_patches(
     let mut visited_properties = HashSet::new();
     let mut changed_properties = HashMap::new();
 
-    let attribute_ref_properties = compute_ref_properties(snapshot, tree);
+    let attribute_ref_properties = compute_ref_properties(snapshot, instance.id(), tree);
 
     let changed_name = if snapshot.name == instance.name() {
         None
@@ -249,9 +249,18 @@ fn compute_children_patches(
 
 fn compute_ref_properties(
     snapshot: &InstanceSnapshot,
+    id: Ref,
     tree: &RojoTree,
 ) -> HashMap<String, Option<Variant>> {
     let mut map = HashMap::new();
+
+    // Targets that can't be found yet might be added by this patch, so they're
+    // resolved again (and reported) once the patch is applied.
+    for (prop_name, path) in &snapshot.metadata.ref_paths {
+        let target = tree.resolve_ref_path(id, path).ok();
+        map.insert(prop_name.clone(), target.map(Variant::Ref));
+    }
+
     let attributes = match snapshot.properties.get("Attributes") {
         Some(Variant::Attributes(attrs)) => attrs,
         _ => return map,
@@ -1,16 +1,18 @@
 use std::{
     collections::{HashMap, VecDeque},
+    mem::take,
     path::{Path, PathBuf},
 };
 
+use anyhow::{bail, format_err};
 use rbx_dom_weak::{
     types::{Ref, Variant},
     Instance, InstanceBuilder, WeakDom,
 };
 
-use crate::{multimap::MultiMap, RojoRef};
+use crate::{multimap::MultiMap, RefPath, RefPathComponent, RojoRef};
 
-use super::{InstanceMetadata, InstanceSnapshot};
+use super::{InstanceMetadata, InstanceSnapshot, InstigatingSource};
 
 /// An expanded variant of rbx_dom_weak's `WeakDom` that tracks additional
 /// metadata per instance that's Rojo-specific.
@@ -39,6 +41,11 @@ pub struct RojoTree {
     /// the same RojoRef for multiple different instances. An entry containing
     /// multiple elements is an error condition that should be raised to the user.
     specified_id_to_refs: MultiMap<RojoRef, Ref>,
+
+    /// Problems found while patches were applied, like Ref properties whose
+    /// targets couldn't be found. These are collected instead of logged so
+    /// that a build can fail because of them while serving only logs them.
+    errors: Vec<String>,
 }
 
 impl RojoTree {
@@ -52,6 +59,7 @@ impl RojoTree {
             metadata_map: HashMap::new(),
             path_to_ids: MultiMap::new(),
             specified_id_to_refs: MultiMap::new(),
+            errors: Vec::new(),
         };
 
         let root_ref = tree.inner.root_ref();
@@ -144,15 +152,11 @@ impl RojoTree {
                         self.path_to_ids.insert(new_path.clone(), id);
                     }
                 }
+                let mut new_specified_id = None;
                 if existing_metadata.specified_id != metadata.specified_id {
-                    // We need to uphold the invariant that each ID can only map
-                    // to one referent.
                     if let Some(new) = &metadata.specified_id {
-                        if !self.specified_id_to_refs.get(new).is_empty() {
-                            log::error!("Duplicate user-specified referent '{new}'");
-                        }
-
                         self.specified_id_to_refs.insert(new.clone(), id);
+                        new_specified_id = Some(new.clone());
                     }
                     if let Some(old) = &existing_metadata.specified_id {
                         self.specified_id_to_refs.remove(old, id);
@@ -160,6 +164,12 @@ impl RojoTree {
                 }
 
                 entry.insert(metadata);
+
+                // We need to uphold the invariant that each ID can only map
+                // to one referent, so anything else is reported.
+                if let Some(new) = new_specified_id {
+                    self.report_duplicate_specified_id(&new);
+                }
             }
             Entry::Vacant(entry) => {
                 entry.insert(metadata);
@@ -191,29 +201,170 @@ impl RojoTree {
         }
     }
 
+    /// Tells whether more than one instance has been given the same RojoRef,
+    /// which means that refs to it can't be resolved.
+    pub fn is_specified_id_ambiguous(&self, specified: &RojoRef) -> bool {
+        self.specified_id_to_refs.get(specified).len() > 1
+    }
+
     pub fn set_specified_id(&mut self, id: Ref, specified: RojoRef) {
         if let Some(metadata) = self.metadata_map.get_mut(&id) {
             if let Some(old) = metadata.specified_id.replace(specified.clone()) {
                 self.specified_id_to_refs.remove(&old, id);
             }
         }
-        self.specified_id_to_refs.insert(specified, id);
+        self.specified_id_to_refs.insert(specified.clone(), id);
+        self.report_duplicate_specified_id(&specified);
     }
 
-    fn insert_metadata(&mut self, id: Ref, metadata: InstanceMetadata) {
-        for path in &metadata.relevant_paths {
-            self.path_to_ids.insert(path.clone(), id);
+    /// Records a problem found while applying a patch to this tree.
+    pub fn report_error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    /// Takes every problem that was reported since the last time this was
+    /// called.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        take(&mut self.errors)
+    }
+
+    /// Reports an error listing every instance that uses the given RojoRef if
+    /// there's more than one of them.
+    fn report_duplicate_specified_id(&mut self, specified: &RojoRef) {
+        let ids = self.specified_id_to_refs.get(specified);
+        if ids.len() < 2 {
+            return;
+        }
+
+        let mut message = format!(
+            "Duplicate ID '{}' was given to {} instances:",
+            specified,
+            ids.len()
+        );
+        for &id in ids {
+            message.push_str(&format!("\n- {}", self.describe_source(id)));
+        }
+        message.push_str("\nRefs to this ID will not be set until only one instance uses it.");
+
+        self.report_error(message);
+    }
+
+    /// Finds the instance that the given `RefPath` points to. Relative paths
+    /// start at the instance with the ID `from`.
+    pub fn resolve_ref_path(&self, from: Ref, path: &RefPath) -> anyhow::Result<Ref> {
+        let mut current = if path.is_absolute() {
+            self.get_root_id()
+        } else {
+            from
+        };
+
+        for component in path.components() {
+            let instance = self
+                .inner
+                .get_by_ref(current)
+                .ok_or_else(|| format_err!("Instance {:?} does not exist in the tree", current))?;
+
+            current = match component {
+                RefPathComponent::Parent => {
+                    if current == self.get_root_id() {
+                        bail!("Ref path {} goes above the root of the tree", path);
+                    }
+
+                    instance.parent()
+                }
+                RefPathComponent::Child(name) => instance
+                    .children()
+                    .iter()
+                    .copied()
+                    .find(|&child| {
+                        self.inner
+                            .get_by_ref(child)
+                            .is_some_and(|child| &child.name == name)
+                    })
+                    .ok_or_else(|| {
+                        format_err!(
+                            "Ref path {} could not be resolved: {} has no child named {}",
+                            path,
+                            self.display_path(current),
+                            name
+                        )
+                    })?,
+            };
         }
 
-        if let Some(specified_id) = &metadata.specified_id {
-            if !self.specified_id_to_refs.get(specified_id).is_empty() {
-                log::error!("Duplicate user-specified referent '{specified_id}'");
+        Ok(current)
+    }
+
+    /// Describes where the instance with the given ID is in the tree, using
+    /// the same format as an absolute `RefPath`.
+    pub fn display_path(&self, id: Ref) -> String {
+        let mut names = Vec::new();
+        let mut current = id;
+
+        while current != self.get_root_id() {
+            match self.inner.get_by_ref(current) {
+                Some(instance) => {
+                    names.push(instance.name.as_str());
+                    current = instance.parent();
+                }
+                None => break,
+            }
+        }
+
+        names.push("@game");
+        names.reverse();
+        names.join("/")
+    }
+
+    /// Describes where the instance with the given ID is in the tree and which
+    /// file it came from, for use in error messages.
+    pub fn describe_source(&self, id: Ref) -> String {
+        let mut current = id;
+
+        // Instances inside of model files don't have their own instigating
+        // source, so we use the closest one above them.
+        let source = loop {
+            if let Some(source) = self
+                .metadata_map
+                .get(&current)
+                .and_then(|metadata| metadata.instigating_source.as_ref())
+            {
+                break Some(source);
             }
 
-            self.set_specified_id(id, specified_id.clone());
+            match self.inner.get_by_ref(current) {
+                Some(instance) if instance.parent().is_some() => current = instance.parent(),
+                _ => break None,
+            }
+        };
+
+        let source_path = match source {
+            Some(InstigatingSource::Path(path)) => path.clone(),
+            Some(InstigatingSource::ProjectNode(project_path, _, node, _)) => {
+                // Nodes with a single $path get most of their contents from
+                // that path, so it's more useful than the project file.
+                match node.path.as_ref().map(|path_node| path_node.paths()) {
+                    Some([path]) => project_path.parent().unwrap().join(path),
+                    _ => project_path.clone(),
+                }
+            }
+            None => return self.display_path(id),
+        };
+
+        format!("{} (from {})", self.display_path(id), source_path.display())
+    }
+
+    fn insert_metadata(&mut self, id: Ref, metadata: InstanceMetadata) {
+        for path in &metadata.relevant_paths {
+            self.path_to_ids.insert(path.clone(), id);
         }
 
+        let specified_id = metadata.specified_id.clone();
         self.metadata_map.insert(id, metadata);
+
+        if let Some(specified_id) = specified_id {
+            self.set_specified_id(id, specified_id);
+        }
     }
 
     /// Moves the Rojo metadata from the instance with the given ID from this
@@ -351,9 +502,11 @@ impl InstanceWithMetaMut<'_> {
 
 #[cfg(test)]
 mod test {
+    use std::path::PathBuf;
+
     use crate::{
         snapshot::{InstanceMetadata, InstanceSnapshot},
-        RojoRef,
+        RefPath, RojoRef,
     };
 
     use super::RojoTree;
@@ -374,4 +527,77 @@ mod test {
         tree.remove(original);
         assert_eq!(tree.get_specified_id(&custom_ref.clone()), Some(duped));
     }
+
+    #[test]
+    fn resolve_ref_paths() {
+        let mut tree = RojoTree::new(InstanceSnapshot::new().name("Root"));
+        let root_id = tree.get_root_id();
+
+        let model = tree.insert_instance(root_id, InstanceSnapshot::new().name("Model"));
+        let handle = tree.insert_instance(model, InstanceSnapshot::new().name("Handle"));
+
+        let resolve =
+            |from, path: &str| tree.resolve_ref_path(from, &path.parse::<RefPath>().unwrap());
+
+        assert_eq!(resolve(model, "./Handle").unwrap(), handle);
+        assert_eq!(resolve(handle, "..").unwrap(), model);
+        assert_eq!(resolve(handle, "@game/Model/Handle").unwrap(), handle);
+        assert_eq!(resolve(handle, "@game").unwrap(), root_id);
+
+        assert_eq!(
+            resolve(model, "./Missing").unwrap_err().to_string(),
+            "Ref path ./Missing could not be resolved: @game/Model has no child named Missing"
+        );
+        assert_eq!(
+            resolve(model, "../..").unwrap_err().to_string(),
+            "Ref path ../.. goes above the root of the tree"
+        );
+    }
+
+    #[test]
+    fn describe_source() {
+        let mut tree = RojoTree::new(InstanceSnapshot::new().name("Root"));
+        let root_id = tree.get_root_id();
+
+        let model = tree.insert_instance(
+            root_id,
+            InstanceSnapshot::new()
+                .name("Model")
+                .metadata(InstanceMetadata::new().instigating_source(PathBuf::from("/Model.rbxm"))),
+        );
+        let part = tree.insert_instance(model, InstanceSnapshot::new().name("Part"));
+
+        assert_eq!(tree.describe_source(root_id), "@game");
+        assert_eq!(
+            tree.describe_source(model),
+            "@game/Model (from /Model.rbxm)"
+        );
+        assert_eq!(
+            tree.describe_source(part),
+            "@game/Model/Part (from /Model.rbxm)"
+        );
+    }
+
+    #[test]
+    fn ambiguous_specified_ids() {
+        let custom_ref = RojoRef::new("MyCoolRef".into());
+        let mut tree = RojoTree::new(InstanceSnapshot::new());
+
+        let first = tree.insert_instance(tree.get_root_id(), InstanceSnapshot::new());
+        let second = tree.insert_instance(tree.get_root_id(), InstanceSnapshot::new());
+
+        tree.set_specified_id(first, custom_ref.clone());
+        assert!(!tree.is_specified_id_ambiguous(&custom_ref));
+
+        // Giving the ID to a second instance makes it ambiguous until one of
+        // them stops using it.
+        let mut metadata = tree.get_metadata(second).unwrap().clone();
+        metadata.specified_id = Some(custom_ref.clone());
+        tree.update_metadata(second, metadata);
+        assert!(tree.is_specified_id_ambiguous(&custom_ref));
+        assert_eq!(tree.get_specified_id(&custom_ref), None);
+
+        tree.update_metadata(second, InstanceMetadata::new());
+        assert_eq!(tree.get_specified_id(&custom_ref), Some(first));
+    }
 }
@@ -0,0 +1,332 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Mutex,
+    thread,
+};
+
+use anyhow::{bail, Context};
+use memofs::Vfs;
+
+use crate::snapshot::{InstanceContext, InstanceSnapshot, SyncRule};
+
+use super::lua::{snapshot_script, ScriptType};
+
+/// The latest output of the command run for each file, along with a hash of
+/// the command and the file's contents, so that files are only processed
+/// again when they change. Only one output is kept per file.
+///
+/// Each serve session has its own cache, which is shared through the
+/// `InstanceContext` of every instance in it.
+#[derive(Default)]
+pub struct CommandOutputCache {
+    outputs: Mutex<HashMap<PathBuf, (blake3::Hash, String)>>,
+}
+
+impl fmt::Debug for CommandOutputCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandOutputCache").finish_non_exhaustive()
+    }
+}
+
+/// The cache never changes what gets snapshotted, so it's ignored when
+/// contexts are compared.
+impl PartialEq for CommandOutputCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// Turns a file into a script by piping its contents through the command
+/// given by a sync rule and using the command's output as the source.
+///
+/// Like with Lua files, files whose names end in `.server` or `.client` once
+/// the rule's suffix is removed become server or client scripts, and all
+/// other files become ModuleScripts.
+pub fn snapshot_command(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    rule: &SyncRule,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let (program, args) = rule.command.split_first().with_context(|| {
+        format!(
+            "Sync rule '{}' uses the command middleware, but doesn't specify a command",
+            rule.include.as_str()
+        )
+    })?;
+
+    if !context.allow_commands {
+        bail!(
+            "Sync rule '{}' runs the command '{}' for {}, but commands are not allowed. \
+             Projects can only run commands when Rojo is run with --allow-commands",
+            rule.include.as_str(),
+            program,
+            path.display()
+        );
+    }
+
+    let contents = vfs.read(path)?;
+    let output = run_command(
+        &context.command_output_cache,
+        path,
+        program,
+        args,
+        &rule.base_path,
+        &contents,
+    )
+    .with_context(|| format!("Could not preprocess {}", path.display()))?;
+    let source = output.replace("\r\n", "\n");
+
+    let (name, script_type) = if let Some(name) = name.strip_suffix(".server") {
+        (name, ScriptType::Server)
+    } else if let Some(name) = name.strip_suffix(".client") {
+        (name, ScriptType::Client)
+    } else {
+        (name, ScriptType::Module)
+    };
+
+    snapshot_script(context, vfs, path, name, script_type, &source)
+}
+
+/// Hashes everything that affects the output of a command.
+fn cache_key(program: &str, args: &[String], working_dir: &Path, input: &[u8]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    let working_dir_str = working_dir.to_string_lossy();
+
+    for part in [program, &working_dir_str]
+        .into_iter()
+        .chain(args.iter().map(String::as_str))
+    {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Runs a command in the given directory with `input`, the contents of the
+/// file at `path`, as its stdin and returns what it wrote to stdout, or an
+/// error with what it wrote to stderr if it failed.
+fn run_command(
+    cache: &CommandOutputCache,
+    path: &Path,
+    program: &str,
+    args: &[String],
+    working_dir: &Path,
+    input: &[u8],
+) -> anyhow::Result<String> {
+    let key = cache_key(program, args, working_dir, input);
+
+    if let Some((cached_key, output)) = cache.outputs.lock().unwrap().get(path) {
+        if *cached_key == key {
+            return Ok(output.clone());
+        }
+    }
+
+    log::debug!("Running command '{}' with args {:?}", program, args);
+
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(working_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run command '{}'", program))?;
+
+    // The input is written from another thread so that commands that write
+    // output before reading all of their input can't deadlock.
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+
+    // Commands are allowed to exit without reading all of their input, which
+    // makes writing to stdin fail, so that error is ignored.
+    let _ = writer.join();
+
+    if !output.status.success() {
+        bail!(
+            "Command '{}' failed ({}):\n{}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+
+    let output = String::from_utf8(output.stdout)
+        .with_context(|| format!("Output of command '{}' was not valid UTF-8", program))?;
+
+    cache
+        .outputs
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (key, output.clone()));
+
+    Ok(output)
+}
+
+// These tests run Cargo, which is always available while they run, and only
+// check whether it succeeds so that they don't depend on what it prints.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    const CARGO: &str = env!("CARGO");
+
+    fn rule(args: &[&str]) -> SyncRule {
+        let command: Vec<_> = std::iter::once(CARGO).chain(args.iter().copied()).collect();
+        let json = format!(
+            r#"{{ "pattern": "*.moon", "use": "command", "command": {} }}"#,
+            serde_json::to_string(&command).unwrap()
+        );
+
+        let mut rule: SyncRule = serde_json::from_str(&json).unwrap();
+        rule.base_path = std::env::temp_dir();
+        rule
+    }
+
+    fn context() -> InstanceContext {
+        let mut context = InstanceContext::with_emit_legacy_scripts(Some(true));
+        context.set_allow_commands(true);
+        context
+    }
+
+    fn snapshot(
+        context: &InstanceContext,
+        path: &str,
+        source: &str,
+        name: &str,
+        rule: &SyncRule,
+    ) -> anyhow::Result<Option<InstanceSnapshot>> {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(path, VfsSnapshot::file(source)).unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        snapshot_command(context, &vfs, Path::new(path), name, rule)
+    }
+
+    fn source(snapshot: &InstanceSnapshot) -> &str {
+        match &snapshot.properties["Source"] {
+            rbx_dom_weak::types::Variant::String(source) => source,
+            other => panic!("Source was not a string: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn module_from_command() {
+        let rule = rule(&["--version"]);
+        let snapshot = snapshot(&context(), "/module.moon", "return 1", "module", &rule)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(snapshot.name, "module");
+        assert_eq!(snapshot.class_name, "ModuleScript");
+        assert!(!source(&snapshot).is_empty());
+        assert!(!source(&snapshot).contains('\r'));
+    }
+
+    #[test]
+    fn server_script_from_command() {
+        let rule = rule(&["--version"]);
+        let snapshot = snapshot(&context(), "/server.moon", "let x = 1", "foo.server", &rule)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(snapshot.name, "foo");
+        assert_eq!(snapshot.class_name, "Script");
+    }
+
+    #[test]
+    fn failing_command() {
+        let rule = rule(&["not-a-real-subcommand"]);
+        let error = snapshot(&context(), "/failing.moon", "let", "foo", &rule).unwrap_err();
+        let message = format!("{:#}", error);
+
+        assert!(
+            message.contains("Could not preprocess /failing.moon"),
+            "{}",
+            message
+        );
+        assert!(message.contains("failed ("), "{}", message);
+    }
+
+    #[test]
+    fn commands_not_allowed() {
+        let rule = rule(&["--version"]);
+        let context = InstanceContext::with_emit_legacy_scripts(Some(true));
+        let error = snapshot(&context, "/not_allowed.moon", "let", "foo", &rule).unwrap_err();
+        let message = format!("{:#}", error);
+
+        assert!(message.contains("commands are not allowed"), "{}", message);
+        assert!(message.contains("--allow-commands"), "{}", message);
+    }
+
+    #[test]
+    fn one_cached_output_per_file() {
+        let rule = rule(&["--version"]);
+        let context = context();
+
+        for source in ["return 1", "return 2", "return 3"] {
+            snapshot(&context, "/cached.moon", source, "foo", &rule).unwrap();
+        }
+
+        let outputs = context.command_output_cache.outputs.lock().unwrap();
+        let (key, _) = &outputs[Path::new("/cached.moon")];
+
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(
+            *key,
+            cache_key(CARGO, &rule.command[1..], &rule.base_path, b"return 3")
+        );
+    }
+
+    #[test]
+    fn command_output_is_cached() {
+        let rule = rule(&["--version"]);
+        let context = context();
+        let key = cache_key(CARGO, &rule.command[1..], &rule.base_path, b"return 1");
+
+        context.command_output_cache.outputs.lock().unwrap().insert(
+            PathBuf::from("/cached_output.moon"),
+            (key, "-- from the cache".to_owned()),
+        );
+
+        let cached = snapshot(&context, "/cached_output.moon", "return 1", "foo", &rule)
+            .unwrap()
+            .unwrap();
+        assert_eq!(source(&cached), "-- from the cache");
+
+        let changed = snapshot(&context, "/cached_output.moon", "return 2", "foo", &rule)
+            .unwrap()
+            .unwrap();
+        assert_ne!(source(&changed), "-- from the cache");
+    }
+
+    #[test]
+    fn sessions_have_separate_caches() {
+        let rule = rule(&["--version"]);
+        let first = context();
+        let second = context();
+
+        snapshot(&first, "/separate.moon", "return 1", "foo", &rule).unwrap();
+
+        assert_eq!(first.command_output_cache.outputs.lock().unwrap().len(), 1);
+        assert!(second
+            .command_output_cache
+            .outputs
+            .lock()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,31 +1,29 @@
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    path::Path,
+};
 
 use anyhow::Context;
 use memofs::{IoResultExt, Vfs};
-use serde::Serialize;
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
 use super::{
+    apply_sync_rule,
     dir::{dir_meta, snapshot_dir_no_meta},
+    localization::{encode_localization_entries, LocalizationEntry},
     meta_file::AdjacentMetadata,
+    util::adjacent_meta_path,
 };
 
 pub fn snapshot_csv(
-    _context: &InstanceContext,
+    context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
     name: &str,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
-    let contents = vfs.read(path)?;
-
-    let table_contents = convert_localization_csv(&contents).with_context(|| {
-        format!(
-            "File was not a valid LocalizationTable CSV file: {}",
-            path.display()
-        )
-    })?;
+    let meta_path = adjacent_meta_path(context, path, name);
+    let table_contents = read_localization_csv(vfs, path)?;
 
     let mut snapshot = InstanceSnapshot::new()
         .name(name)
@@ -37,9 +35,11 @@ pub fn snapshot_csv(
                 .relevant_paths(vec![path.to_path_buf(), meta_path.clone()]),
         );
 
+    apply_sync_rule(context, vfs, path, &mut snapshot)?;
+
     if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
         let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
-        metadata.apply_all(&mut snapshot)?;
+        metadata.apply_all(vfs, &mut snapshot)?;
     }
 
     Ok(Some(snapshot))
@@ -69,39 +69,40 @@ pub fn snapshot_csv_init(
         );
     }
 
+    // The init.csv is read through snapshot_csv, so it's converted and
+    // checked for problems just like every other localization CSV.
     let mut init_snapshot = snapshot_csv(context, vfs, init_path, &dir_snapshot.name)?.unwrap();
 
     init_snapshot.children = dir_snapshot.children;
     init_snapshot.metadata = dir_snapshot.metadata;
 
     if let Some(mut meta) = dir_meta(vfs, folder_path)? {
-        meta.apply_all(&mut init_snapshot)?;
+        meta.apply_all(vfs, &mut init_snapshot)?;
     }
 
     Ok(Some(init_snapshot))
 }
 
-/// Struct that holds any valid row from a Roblox CSV translation table.
-///
-/// We manually deserialize into this table from CSV, but let serde_json handle
-/// serialization.
-#[derive(Debug, Default, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct LocalizationEntry<'a> {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    key: Option<&'a str>,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    context: Option<&'a str>,
+/// Reads the localization CSV at `path` and converts it into the contents of
+/// a LocalizationTable, warning about any problems that `rojo localization`
+/// would report, like duplicate keys or unknown locales.
+fn read_localization_csv(vfs: &Vfs, path: &Path) -> anyhow::Result<String> {
+    let contents = vfs.read(path)?;
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    example: Option<&'a str>,
+    let table_contents = convert_localization_csv(&contents).with_context(|| {
+        format!(
+            "File was not a valid LocalizationTable CSV file: {}",
+            path.display()
+        )
+    })?;
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    source: Option<&'a str>,
+    if let Ok(report) = validate_localization_csv(&contents) {
+        for problem in report.problems() {
+            log::warn!("{}: {}", path.display(), problem);
+        }
+    }
 
-    // We use a BTreeMap here to get deterministic output order.
-    values: BTreeMap<&'a str, &'a str>,
+    Ok(table_contents)
 }
 
 /// Normally, we'd be able to let the csv crate construct our struct for us.
@@ -130,34 +131,191 @@ fn convert_localization_csv(contents: &[u8]) -> Result<String, csv::Error> {
         let mut entry = LocalizationEntry::default();
 
         for (header, value) in headers.iter().zip(record.into_iter()) {
-            if header.is_empty() || value.is_empty() {
-                continue;
-            }
-
-            match header {
-                "Key" => entry.key = Some(value),
-                "Source" => entry.source = Some(value),
-                "Context" => entry.context = Some(value),
-                "Example" => entry.example = Some(value),
-                _ => {
-                    entry.values.insert(header, value);
-                }
-            }
+            entry.set_column(header, value);
         }
 
-        if entry.key.is_none() && entry.source.is_none() {
+        if !entry.is_valid() {
             continue;
         }
 
         entries.push(entry);
     }
 
-    let encoded =
-        serde_json::to_string(&entries).expect("Could not encode JSON for localization table");
+    let encoded = encode_localization_entries(&entries);
 
     Ok(encoded)
 }
 
+/// Locales that Roblox can translate into. Locale columns are made of one of
+/// these, optionally followed by a region or script, like `es-mx` or
+/// `zh-hans`.
+const KNOWN_LANGUAGES: &[&str] = &[
+    "ar", "bg", "bn", "bs", "cs", "da", "de", "el", "en", "es", "et", "fi", "fil", "fr", "he",
+    "hi", "hr", "hu", "id", "it", "ja", "ka", "kk", "km", "ko", "lo", "lt", "lv", "ms", "my", "nb",
+    "nl", "pl", "pt", "ro", "ru", "si", "sk", "sl", "sq", "sr", "sv", "th", "tr", "uk", "vi", "zh",
+];
+
+fn is_known_locale(locale: &str) -> bool {
+    let (language, rest) = match locale.split_once('-') {
+        Some((language, rest)) => (language, Some(rest)),
+        None => (locale, None),
+    };
+
+    let valid_rest = rest.map_or(true, |rest| {
+        (2..=4).contains(&rest.len())
+            && rest
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    });
+
+    KNOWN_LANGUAGES.contains(&language) && valid_rest
+}
+
+/// How many entries are listed by name when describing missing translations.
+const MAX_LISTED_ENTRIES: usize = 10;
+
+/// Problems in a localization CSV that don't stop it from building, but are
+/// likely to break in game.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LocalizationReport {
+    /// Entries that use the same key, or the same source and context, as an
+    /// earlier entry. Roblox only uses one of them.
+    pub duplicates: Vec<String>,
+
+    /// Columns that should be locales, but aren't ones Roblox recognizes.
+    pub unknown_locales: Vec<String>,
+
+    /// The entries missing a translation, for each locale column.
+    pub missing_translations: BTreeMap<String, Vec<String>>,
+
+    /// The number of entries in the table.
+    pub entry_count: usize,
+}
+
+impl LocalizationReport {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty()
+            && self.unknown_locales.is_empty()
+            && self.missing_translations.is_empty()
+    }
+
+    /// Describes each problem in the report in one line.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = self.duplicates.clone();
+
+        for locale in &self.unknown_locales {
+            problems.push(format!(
+                "Column \"{}\" is not a locale that Roblox recognizes",
+                locale
+            ));
+        }
+
+        for (locale, missing) in &self.missing_translations {
+            let mut names = missing
+                .iter()
+                .take(MAX_LISTED_ENTRIES)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            if missing.len() > MAX_LISTED_ENTRIES {
+                names.push_str(&format!(
+                    ", and {} more",
+                    missing.len() - MAX_LISTED_ENTRIES
+                ));
+            }
+
+            problems.push(format!(
+                "Locale {} is missing {} of {} translations: {}",
+                locale,
+                missing.len(),
+                self.entry_count,
+                names
+            ));
+        }
+
+        problems
+    }
+}
+
+/// Checks a localization CSV for duplicate entries, unknown locale columns,
+/// and missing translations. Entries are described by their key if they have
+/// one, and by their source otherwise.
+pub fn validate_localization_csv(contents: &[u8]) -> Result<LocalizationReport, csv::Error> {
+    let mut reader = csv::Reader::from_reader(contents);
+    let headers = reader.headers()?.clone();
+
+    let locales: Vec<&str> = headers
+        .iter()
+        .filter(|header| !matches!(*header, "" | "Key" | "Source" | "Context" | "Example"))
+        .collect();
+
+    let mut report = LocalizationReport {
+        unknown_locales: locales
+            .iter()
+            .filter(|locale| !is_known_locale(locale))
+            .map(|locale| locale.to_string())
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut seen = HashMap::new();
+
+    for record in reader.into_records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+
+        let mut entry = LocalizationEntry::default();
+        for (header, value) in headers.iter().zip(record.iter()) {
+            entry.set_column(header, value);
+        }
+
+        if !entry.is_valid() {
+            continue;
+        }
+
+        report.entry_count += 1;
+
+        // Entries are identified by their key if they have one, and by their
+        // source and context otherwise.
+        let description = match (&entry.key, &entry.source, &entry.context) {
+            (Some(key), _, _) => format!("key \"{}\"", key),
+            (None, Some(source), Some(context)) => {
+                format!("source \"{}\" (context \"{}\")", source, context)
+            }
+            (None, Some(source), None) => format!("source \"{}\"", source),
+            (None, None, _) => unreachable!("entry was checked to be valid"),
+        };
+
+        match seen.entry(description) {
+            Entry::Occupied(first) => report.duplicates.push(format!(
+                "Duplicate {} on line {} (first used on line {})",
+                first.key(),
+                line,
+                first.get()
+            )),
+            Entry::Vacant(vacant) => {
+                vacant.insert(line);
+            }
+        }
+
+        // Unknown locales are already reported, so listing every entry as
+        // missing from them too would only add noise.
+        let name = entry.key.as_ref().or(entry.source.as_ref()).unwrap();
+        for locale in locales.iter().filter(|locale| is_known_locale(locale)) {
+            if !entry.values.contains_key(*locale) {
+                report
+                    .missing_translations
+                    .entry(locale.to_string())
+                    .or_default()
+                    .push(name.to_string());
+            }
+        }
+    }
+
+    Ok(report)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -222,4 +380,44 @@ Ack,Ack!,,An exclamation of despair,¡Ay!"#,
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn validate_csv() {
+        let report = validate_localization_csv(
+            "Key,Source,Context,Example,es,spanish,zh-hans\n\
+             Greeting,Hello!,,,¡Hola!,,你好\n\
+             Greeting,Hi!,,,,,嗨\n\
+             ,Bye,Menu,,Adiós,,再见\n\
+             ,Bye,Menu,,Adiós,,再见\n\
+             ,Bye,Shop,,Adiós,,再见\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            report,
+            LocalizationReport {
+                duplicates: vec![
+                    "Duplicate key \"Greeting\" on line 3 (first used on line 2)".to_owned(),
+                    "Duplicate source \"Bye\" (context \"Menu\") on line 5 (first used on line 4)"
+                        .to_owned(),
+                ],
+                unknown_locales: vec!["spanish".to_owned()],
+                missing_translations: [("es".to_owned(), vec!["Greeting".to_owned()])].into(),
+                entry_count: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn validate_csv_no_problems() {
+        let report = validate_localization_csv(
+            "Key,Source,Context,Example,es,pt-br\n\
+             Ack,Ack!,,An exclamation of despair,¡Ay!,Ai!\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert!(report.is_empty(), "{:?}", report.problems());
+    }
 }
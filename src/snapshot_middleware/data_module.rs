@@ -0,0 +1,254 @@
+//! Shared code for middleware that turn data files, like JSON, TOML, and YAML,
+//! into ModuleScripts that return their data.
+
+use std::path::Path;
+
+use anyhow::Context;
+use memofs::{IoResultExt, Vfs};
+
+use crate::{
+    lua_ast::{Chunk, Expression, Statement, Type},
+    snapshot::{DataModuleOptions, InstanceContext, InstanceMetadata, InstanceSnapshot},
+};
+
+use super::{apply_sync_rule, meta_file::AdjacentMetadata, util::adjacent_meta_path};
+
+/// Creates a ModuleScript that returns the given value, which was read from
+/// the data file at `path`. The context's `dataModules` options, which the
+/// file's meta file can override, decide whether the module exports a type
+/// for its data and whether that data is frozen.
+///
+/// Strings in the data that are `@asset/` references are replaced with the
+/// content URL of the asset they refer to.
+pub fn snapshot_data_module(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    mut value: Expression,
+) -> anyhow::Result<InstanceSnapshot> {
+    resolve_assets(context, &mut value)
+        .with_context(|| format!("Could not resolve assets in {}", path.display()))?;
+
+    let meta_path = adjacent_meta_path(context, path, name);
+
+    let mut metadata = match vfs.read(&meta_path).with_not_found()? {
+        Some(meta_contents) => Some(AdjacentMetadata::from_slice(
+            &meta_contents,
+            meta_path.clone(),
+        )?),
+        None => None,
+    };
+
+    let options = match metadata.as_mut().and_then(|meta| meta.data_modules.take()) {
+        Some(data_modules) => context.data_modules.merge(data_modules),
+        None => context.data_modules,
+    };
+
+    let source = data_module_source(value, name, options);
+    let properties = [("Source".to_owned(), source.into())];
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("ModuleScript")
+        .properties(properties)
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path])
+                .context(context),
+        );
+
+    apply_sync_rule(context, vfs, path, &mut snapshot)?;
+
+    if let Some(mut metadata) = metadata {
+        metadata.apply_all(vfs, &mut snapshot)?;
+    }
+
+    Ok(snapshot)
+}
+
+fn resolve_assets(context: &InstanceContext, value: &mut Expression) -> anyhow::Result<()> {
+    match value {
+        Expression::String(string) => {
+            if let Some(resolved) = context.resolve_asset(string)? {
+                *string = resolved;
+            }
+        }
+        Expression::Table(table) => {
+            for (_, value) in &mut table.entries {
+                resolve_assets(context, value)?;
+            }
+        }
+        Expression::Array(values) => {
+            for value in values {
+                resolve_assets(context, value)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn data_module_source(value: Expression, name: &str, options: DataModuleOptions) -> String {
+    // The type has to be inferred before freezing, since frozen tables are
+    // function calls rather than table literals.
+    let data_type = options.export_type().then(|| Type::infer(&value));
+
+    let value = if options.freeze() {
+        value.deep_freeze()
+    } else {
+        value
+    };
+
+    match data_type {
+        Some(data_type) => {
+            let type_name = type_name(name);
+
+            Chunk(vec![
+                Statement::ExportType(type_name.clone(), data_type),
+                Statement::Local("data".to_owned(), Some(Type::Named(type_name)), value),
+                Statement::Return(Expression::Name("data".to_owned())),
+            ])
+            .to_string()
+        }
+        None => Statement::Return(value).to_string(),
+    }
+}
+
+/// Turns an instance name like `item-drops` into a type name like `ItemDrops`,
+/// falling back to `Data` if there's nothing usable in the name.
+fn type_name(name: &str) -> String {
+    let type_name: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect();
+
+    match type_name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => type_name,
+        _ => "Data".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::{asset_manifest::AssetManifest, snapshot_middleware::json::snapshot_json};
+
+    fn context_with(data_modules: DataModuleOptions) -> InstanceContext {
+        let mut context = InstanceContext::default();
+        context.merge_data_modules(data_modules);
+        context
+    }
+
+    #[test]
+    fn export_type_and_freeze() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/item-drops.json",
+            VfsSnapshot::file(
+                r#"{
+                    "rolls": 3,
+                    "drops": [
+                        { "id": "sword", "weight": 1 },
+                        { "id": "shield", "weight": 2, "rare": true }
+                    ]
+                }"#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_json(
+            &context_with(DataModuleOptions {
+                export_type: Some(true),
+                freeze: Some(true),
+            }),
+            &vfs,
+            Path::new("/item-drops.json"),
+            "item-drops",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn meta_file_overrides_context() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.json", VfsSnapshot::file(r#"{ "list": [1, 2] }"#))
+            .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(r#"{ "dataModules": { "exportType": true } }"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_json(
+            &context_with(DataModuleOptions {
+                export_type: None,
+                freeze: Some(true),
+            }),
+            &vfs,
+            Path::new("/foo.json"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn unknown_asset() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/assets.lock.json",
+            VfsSnapshot::file(r#"{ "assets": { "icon.png": 1818 } }"#),
+        )
+        .unwrap();
+        imfs.load_snapshot(
+            "/foo.json",
+            VfsSnapshot::file(r#"{ "icons": ["@asset/icon.png", "@asset/missing.png"] }"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let mut context = InstanceContext::default();
+        context
+            .set_asset_manifest(AssetManifest::load(&vfs, Path::new("/assets.lock.json")).unwrap());
+
+        let error = snapshot_json(&context, &vfs, Path::new("/foo.json"), "foo").unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", error),
+            "Could not resolve assets in /foo.json: Unknown asset \"@asset/missing.png\": \
+             it is not listed in the asset manifest /assets.lock.json"
+        );
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(type_name("config"), "Config");
+        assert_eq!(type_name("item-drops"), "ItemDrops");
+        assert_eq!(type_name("shop_items v2"), "ShopItemsV2");
+        assert_eq!(type_name("2024"), "Data");
+        assert_eq!(type_name("¡Hola!"), "Hola");
+    }
+}
@@ -1,10 +1,10 @@
-use std::path::Path;
+use std::{borrow::Cow, path::Path};
 
 use memofs::{DirEntry, IoResultExt, Vfs};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::{meta_file::DirectoryMetadata, snapshot_from_vfs};
+use super::{meta_file::DirectoryMetadata, snapshot_from_vfs, util::instance_name_from_file_name};
 
 pub fn snapshot_dir(
     context: &InstanceContext,
@@ -17,7 +17,7 @@ pub fn snapshot_dir(
     };
 
     if let Some(mut meta) = dir_meta(vfs, path)? {
-        meta.apply_all(&mut snapshot)?;
+        meta.apply_all(vfs, &mut snapshot)?;
     }
 
     Ok(Some(snapshot))
@@ -36,6 +36,36 @@ pub fn dir_meta(vfs: &Vfs, path: &Path) -> anyhow::Result<Option<DirectoryMetada
     }
 }
 
+/// Returns the context that should be used to snapshot this directory and its
+/// contents, which its meta file can change.
+pub fn dir_context<'a>(
+    context: &'a InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+) -> anyhow::Result<Cow<'a, InstanceContext>> {
+    let meta = match dir_meta(vfs, path)? {
+        Some(meta) => meta,
+        None => return Ok(Cow::Borrowed(context)),
+    };
+
+    let mut dir_context = Cow::Borrowed(context);
+
+    match meta.emit_legacy_scripts {
+        Some(emit) if emit != context.emit_legacy_scripts => {
+            dir_context.to_mut().set_emit_legacy_scripts(emit);
+        }
+        _ => {}
+    }
+
+    if let Some(data_modules) = meta.data_modules {
+        if context.data_modules.merge(data_modules) != context.data_modules {
+            dir_context.to_mut().merge_data_modules(data_modules);
+        }
+    }
+
+    Ok(dir_context)
+}
+
 /// Snapshot a directory without applying meta files; useful for if the
 /// directory's ClassName will change before metadata should be applied. For
 /// example, this can happen if the directory contains an `init.client.lua`
@@ -66,16 +96,16 @@ pub fn snapshot_dir_no_meta(
         }
     }
 
-    let instance_name = path
+    let file_name = path
         .file_name()
         .expect("Could not extract file name")
         .to_str()
-        .ok_or_else(|| anyhow::anyhow!("File name was not valid UTF-8: {}", path.display()))?
-        .to_string();
+        .ok_or_else(|| anyhow::anyhow!("File name was not valid UTF-8: {}", path.display()))?;
+    let instance_name = instance_name_from_file_name(context, file_name).into_owned();
 
     let meta_path = path.join("init.meta.json");
 
-    let relevant_paths = vec![
+    let mut relevant_paths = vec![
         path.to_path_buf(),
         meta_path,
         // TODO: We shouldn't need to know about Lua existing in this
@@ -88,7 +118,14 @@ pub fn snapshot_dir_no_meta(
         path.join("init.client.lua"),
         path.join("init.client.luau"),
         path.join("init.csv"),
+        path.join("init.pot"),
     ];
+    relevant_paths.extend(
+        context
+            .init_file_rules
+            .iter()
+            .map(|rule| path.join(&rule.name)),
+    );
 
     let snapshot = InstanceSnapshot::new()
         .name(instance_name)
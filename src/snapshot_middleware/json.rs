@@ -1,14 +1,14 @@
 use std::path::Path;
 
 use anyhow::Context;
-use memofs::{IoResultExt, Vfs};
+use memofs::Vfs;
 
 use crate::{
-    lua_ast::{Expression, Statement},
-    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
+    lua_ast::Expression,
+    snapshot::{InstanceContext, InstanceSnapshot},
 };
 
-use super::meta_file::AdjacentMetadata;
+use super::data_module::snapshot_data_module;
 
 pub fn snapshot_json(
     context: &InstanceContext,
@@ -21,33 +21,11 @@ pub fn snapshot_json(
     let value: serde_json::Value = serde_json::from_slice(&contents)
         .with_context(|| format!("File contains malformed JSON: {}", path.display()))?;
 
-    let as_lua = json_to_lua(value).to_string();
+    let as_lua = json_to_lua_value(value);
 
-    let properties = [("Source".to_owned(), as_lua.into())];
-
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
-
-    let mut snapshot = InstanceSnapshot::new()
-        .name(name)
-        .class_name("ModuleScript")
-        .properties(properties)
-        .metadata(
-            InstanceMetadata::new()
-                .instigating_source(path)
-                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
-                .context(context),
-        );
-
-    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
-        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
-        metadata.apply_all(&mut snapshot)?;
-    }
-
-    Ok(Some(snapshot))
-}
-
-fn json_to_lua(value: serde_json::Value) -> Statement {
-    Statement::Return(json_to_lua_value(value))
+    Ok(Some(snapshot_data_module(
+        context, vfs, path, name, as_lua,
+    )?))
 }
 
 fn json_to_lua_value(value: serde_json::Value) -> Expression {
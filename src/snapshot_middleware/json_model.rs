@@ -1,4 +1,9 @@
-use std::{borrow::Cow, collections::HashMap, path::Path, str};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str,
+};
 
 use anyhow::Context;
 use memofs::Vfs;
@@ -7,7 +12,7 @@ use serde::Deserialize;
 
 use crate::{
     resolution::UnresolvedValue,
-    snapshot::{InstanceContext, InstanceSnapshot},
+    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
     RojoRef,
 };
 
@@ -16,6 +21,69 @@ pub fn snapshot_json_model(
     vfs: &Vfs,
     path: &Path,
     name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    snapshot_model(context, vfs, path, name, ModelFormat::Json)
+}
+
+pub fn snapshot_yaml_model(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    snapshot_model(context, vfs, path, name, ModelFormat::Yaml)
+}
+
+pub fn snapshot_toml_model(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    snapshot_model(context, vfs, path, name, ModelFormat::Toml)
+}
+
+/// The file formats that models can be written in. All of them share the
+/// schema defined by `JsonModel`.
+#[derive(Debug, Clone, Copy)]
+enum ModelFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ModelFormat {
+    fn display_name(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "model.json",
+            Self::Yaml => "model.yaml",
+            Self::Toml => "model.toml",
+        }
+    }
+
+    fn parse(self, contents: &str) -> anyhow::Result<JsonModel> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(contents)?,
+            Self::Yaml => serde_yaml::from_str(contents)?,
+            Self::Toml => toml::from_str(contents)?,
+        })
+    }
+}
+
+fn snapshot_model(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    format: ModelFormat,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
     let contents = vfs.read(path)?;
     let contents_str = str::from_utf8(&contents)
@@ -25,11 +93,16 @@ pub fn snapshot_json_model(
         return Ok(None);
     }
 
-    let mut instance: JsonModel = serde_json::from_str(contents_str)
-        .with_context(|| format!("File is not a valid JSON model: {}", path.display()))?;
+    let mut instance = format.parse(contents_str).with_context(|| {
+        format!(
+            "File is not a valid {} model: {}",
+            format.display_name(),
+            path.display()
+        )
+    })?;
 
     if let Some(top_level_name) = &instance.name {
-        let new_name = format!("{}.model.json", top_level_name);
+        let new_name = format!("{}.{}", top_level_name, format.extension());
 
         log::warn!(
             "Model at path {} had a top-level Name field. \
@@ -44,14 +117,21 @@ pub fn snapshot_json_model(
 
     let id = instance.id.take().map(RojoRef::new);
 
+    let mut relevant_paths = vec![path.to_path_buf()];
     let mut snapshot = instance
-        .into_snapshot()
-        .with_context(|| format!("Could not load JSON model: {}", path.display()))?;
+        .into_snapshot(vfs, path.parent().unwrap(), &mut relevant_paths)
+        .with_context(|| {
+            format!(
+                "Could not load {} model: {}",
+                format.display_name(),
+                path.display()
+            )
+        })?;
 
     snapshot.metadata = snapshot
         .metadata
         .instigating_source(path)
-        .relevant_paths(vec![path.to_path_buf()])
+        .relevant_paths(relevant_paths)
         .context(context)
         .specified_id(id);
 
@@ -92,17 +172,32 @@ struct JsonModel {
 }
 
 impl JsonModel {
-    fn into_snapshot(self) -> anyhow::Result<InstanceSnapshot> {
+    /// Turns this model into a snapshot. Paths of any files read for property
+    /// values are pushed into `relevant_paths`.
+    fn into_snapshot(
+        self,
+        vfs: &Vfs,
+        base_path: &Path,
+        relevant_paths: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<InstanceSnapshot> {
         let name = self.name.unwrap_or_else(|| self.class_name.clone());
         let class_name = self.class_name;
 
         let mut children = Vec::with_capacity(self.children.len());
         for child in self.children {
-            children.push(child.into_snapshot()?);
+            children.push(child.into_snapshot(vfs, base_path, relevant_paths)?);
         }
 
+        let mut metadata = InstanceMetadata::default();
         let mut properties = HashMap::with_capacity(self.properties.len());
-        for (key, unresolved) in self.properties {
+        for (key, mut unresolved) in self.properties {
+            relevant_paths.extend(unresolved.load_file(vfs, base_path)?);
+
+            if let Some(ref_path) = unresolved.ref_path(&class_name, &key)? {
+                metadata.ref_paths.insert(key, ref_path);
+                continue;
+            }
+
             let value = unresolved.resolve(&class_name, &key)?;
             properties.insert(key, value);
         }
@@ -110,7 +205,9 @@ impl JsonModel {
         if !self.attributes.is_empty() {
             let mut attributes = Attributes::new();
 
-            for (key, unresolved) in self.attributes {
+            for (key, mut unresolved) in self.attributes {
+                relevant_paths.extend(unresolved.load_file(vfs, base_path)?);
+
                 let value = unresolved.resolve_unambiguous()?;
                 attributes.insert(key, value);
             }
@@ -120,7 +217,7 @@ impl JsonModel {
 
         Ok(InstanceSnapshot {
             snapshot_id: Ref::none(),
-            metadata: Default::default(),
+            metadata,
             name: Cow::Owned(name),
             class_name: Cow::Owned(class_name),
             properties,
@@ -210,4 +307,70 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn model_from_vfs_yaml() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.model.yaml",
+            VfsSnapshot::file(
+                r#"
+                    className: IntValue
+                    properties:
+                      Value: 5
+                    children:
+                      - name: The Child
+                        className: StringValue
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_yaml_model(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.model.yaml"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn model_from_vfs_toml() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.model.toml",
+            VfsSnapshot::file(
+                r#"
+                    className = "IntValue"
+
+                    [properties]
+                    Value = 5
+
+                    [[children]]
+                    name = "The Child"
+                    className = "StringValue"
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_toml_model(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.model.toml"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
 }
@@ -0,0 +1,104 @@
+//! The entry model shared by every file format that turns into a
+//! LocalizationTable, so that the same data produces the same `Contents` no
+//! matter which format it was written in.
+
+use std::{borrow::Cow, collections::BTreeMap, path::Path};
+
+use memofs::{IoResultExt, Vfs};
+use serde::Serialize;
+
+use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+
+use super::{apply_sync_rule, meta_file::AdjacentMetadata, util::adjacent_meta_path};
+
+/// Struct that holds any valid entry from a Roblox translation table.
+///
+/// Formats deserialize into this manually, but serde_json handles
+/// serialization.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizationEntry<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example: Option<Cow<'a, str>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Cow<'a, str>>,
+
+    // We use a BTreeMap here to get deterministic output order.
+    pub values: BTreeMap<Cow<'a, str>, Cow<'a, str>>,
+}
+
+impl<'a> LocalizationEntry<'a> {
+    /// Sets the field for the given column, using the column names from
+    /// localization CSV files. Any column that isn't `Key`, `Source`,
+    /// `Context`, or `Example` is a locale. Empty columns and values are
+    /// skipped.
+    pub fn set_column(&mut self, column: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) {
+        let column = column.into();
+        let value = value.into();
+
+        if column.is_empty() || value.is_empty() {
+            return;
+        }
+
+        match column.as_ref() {
+            "Key" => self.key = Some(value),
+            "Source" => self.source = Some(value),
+            "Context" => self.context = Some(value),
+            "Example" => self.example = Some(value),
+            _ => {
+                self.values.insert(column, value);
+            }
+        }
+    }
+
+    /// Tells whether this entry can be looked up, which requires either a key
+    /// or a source string. Entries without either are dropped.
+    pub fn is_valid(&self) -> bool {
+        self.key.is_some() || self.source.is_some()
+    }
+}
+
+/// Encodes entries into the JSON used by the `Contents` property of
+/// LocalizationTable.
+pub fn encode_localization_entries(entries: &[LocalizationEntry]) -> String {
+    serde_json::to_string(entries).expect("Could not encode JSON for localization table")
+}
+
+/// Creates a LocalizationTable snapshot with the given `Contents`, applying
+/// the file's adjacent meta file if it has one.
+pub fn snapshot_localization_table(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    table_contents: String,
+) -> anyhow::Result<InstanceSnapshot> {
+    let meta_path = adjacent_meta_path(context, path, name);
+
+    let mut snapshot = InstanceSnapshot::new()
+        .name(name)
+        .class_name("LocalizationTable")
+        .properties([("Contents".to_owned(), table_contents.into())])
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf(), meta_path.clone()])
+                .context(context),
+        );
+
+    apply_sync_rule(context, vfs, path, &mut snapshot)?;
+
+    if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
+        let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
+        metadata.apply_all(vfs, &mut snapshot)?;
+    }
+
+    Ok(snapshot)
+}
@@ -0,0 +1,202 @@
+//! Turns localization JSON files into LocalizationTables.
+//!
+//! These files map each key to its entry, which uses the same names as the
+//! columns of a localization CSV file:
+//!
+//! ```json
+//! {
+//!     "Greeting": {
+//!         "Source": "Hello!",
+//!         "Example": "Shown when a player joins",
+//!         "es": "¡Hola!"
+//!     }
+//! }
+//! ```
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use anyhow::Context;
+use memofs::Vfs;
+use serde::{
+    de::{IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::snapshot::{InstanceContext, InstanceSnapshot};
+
+use super::localization::{
+    encode_localization_entries, snapshot_localization_table, LocalizationEntry,
+};
+
+pub fn snapshot_localization_json(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read(path)?;
+
+    let table_contents = convert_localization_json(&contents).with_context(|| {
+        format!(
+            "File was not a valid LocalizationTable JSON file: {}",
+            path.display()
+        )
+    })?;
+
+    Ok(Some(snapshot_localization_table(
+        context,
+        vfs,
+        path,
+        name,
+        table_contents,
+    )?))
+}
+
+fn convert_localization_json(contents: &[u8]) -> anyhow::Result<String> {
+    let table: LocalizationJson = serde_json::from_slice(contents)?;
+
+    let mut entries = Vec::with_capacity(table.0.len());
+
+    for (key, columns) in &table.0 {
+        let mut entry = LocalizationEntry::default();
+        entry.set_column("Key", key.as_str());
+
+        for (column, value) in columns {
+            entry.set_column(column.as_str(), value.as_str());
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(encode_localization_entries(&entries))
+}
+
+/// The entries of a localization JSON file, in the order they were written so
+/// that the output matches an equivalent CSV file.
+struct LocalizationJson(Vec<(String, BTreeMap<String, String>)>);
+
+impl<'de> Deserialize<'de> for LocalizationJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = LocalizationJson;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object mapping keys to localization entries")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "$schema" {
+                        map.next_value::<IgnoredAny>()?;
+                    } else {
+                        entries.push((key, map.next_value()?));
+                    }
+                }
+
+                Ok(LocalizationJson(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::snapshot_middleware::csv::snapshot_csv;
+
+    #[test]
+    fn localization_json_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.localization.json",
+            VfsSnapshot::file(
+                r#"{
+                    "Ack": {
+                        "Source": "Ack!",
+                        "Example": "An exclamation of despair",
+                        "es": "¡Ay!"
+                    }
+                }"#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_localization_json(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.localization.json"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn same_output_as_csv() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.localization.json",
+            VfsSnapshot::file(
+                r#"{
+                    "$schema": "https://example.com/localization.schema.json",
+                    "Zebra": { "Source": "Zebra", "es": "Cebra" },
+                    "Ack": {
+                        "Source": "Ack!",
+                        "Context": "Menu",
+                        "Example": "An exclamation of despair",
+                        "es": "¡Ay!",
+                        "fr": ""
+                    }
+                }"#,
+            ),
+        )
+        .unwrap();
+        imfs.load_snapshot(
+            "/foo.csv",
+            VfsSnapshot::file(
+                "Key,Source,Context,Example,es,fr\n\
+                 Zebra,Zebra,,,Cebra,\n\
+                 Ack,Ack!,Menu,An exclamation of despair,¡Ay!,\n",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let json_snapshot = snapshot_localization_json(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.localization.json"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        let csv_snapshot = snapshot_csv(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.csv"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            json_snapshot.properties.get("Contents"),
+            csv_snapshot.properties.get("Contents")
+        );
+    }
+}
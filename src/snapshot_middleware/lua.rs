@@ -1,13 +1,19 @@
 use std::{collections::HashMap, path::Path, str};
 
+use anyhow::{format_err, Context};
 use memofs::{IoResultExt, Vfs};
 use rbx_dom_weak::types::Enum;
 
-use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+use crate::{
+    resolution::UnresolvedValue,
+    snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot},
+};
 
 use super::{
+    apply_sync_rule,
     dir::{dir_meta, snapshot_dir_no_meta},
     meta_file::AdjacentMetadata,
+    util::adjacent_meta_path,
 };
 
 #[derive(Debug)]
@@ -25,7 +31,22 @@ pub fn snapshot_lua(
     name: &str,
     script_type: ScriptType,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
-    let run_context_enums = &rbx_reflection_database::get()
+    let contents = vfs.read_to_string_lf_normalized(path)?;
+
+    snapshot_script(context, vfs, path, name, script_type, &contents)
+}
+
+/// Turns the given source into a script snapshot as if it had been read from
+/// the file at `path`, including applying any directives and meta file.
+pub fn snapshot_script(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    script_type: ScriptType,
+    contents_str: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let run_context_enums = &crate::reflection::database()
         .enums
         .get("RunContext")
         .expect("Unable to get RunContext enums!")
@@ -39,11 +60,15 @@ pub fn snapshot_lua(
         (_, ScriptType::Module) => ("ModuleScript", None),
     };
 
-    let contents = vfs.read_to_string_lf_normalized(path)?;
-    let contents_str = contents.as_str();
+    let source = match &context.luau_transforms {
+        Some(transforms) => transforms
+            .apply(contents_str, &context.declared_constants)
+            .with_context(|| format!("Could not apply build profile to {}", path.display()))?,
+        None => contents_str.to_owned(),
+    };
 
     let mut properties = HashMap::with_capacity(2);
-    properties.insert("Source".to_owned(), contents_str.into());
+    properties.insert("Source".to_owned(), source.into());
 
     if let Some(run_context) = run_context {
         properties.insert(
@@ -52,7 +77,7 @@ pub fn snapshot_lua(
         );
     }
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+    let meta_path = adjacent_meta_path(context, path, name);
 
     let mut snapshot = InstanceSnapshot::new()
         .name(name)
@@ -65,14 +90,136 @@ pub fn snapshot_lua(
                 .context(context),
         );
 
+    // The sync rule is applied first, then directives, so that the meta file
+    // takes precedence over both.
+    apply_sync_rule(context, vfs, path, &mut snapshot)?;
+
+    if let Some(mut directives) = parse_directives(contents_str, path)? {
+        directives.apply_all(vfs, &mut snapshot)?;
+    }
+
     if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
         let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
-        metadata.apply_all(&mut snapshot)?;
+        metadata.apply_all(vfs, &mut snapshot)?;
     }
 
     Ok(Some(snapshot))
 }
 
+/// Parses `--!rojo` directives from the comments at the top of a script,
+/// which set properties and attributes the same way a meta file would:
+///
+/// ```lua
+/// --!strict
+/// --!rojo disabled=true tags=Enemy,Boss attribute:Health=100
+/// ```
+///
+/// Each directive is a `key=value` pair. Keys name a property, with the first
+/// letter capitalized if it isn't already, except for `tags`, which takes a
+/// comma-separated list, and `attribute:Name`, which sets an attribute.
+/// Values are read as JSON if possible and as a plain string otherwise, so
+/// strings with spaces can be written with quotes.
+fn parse_directives(source: &str, path: &Path) -> anyhow::Result<Option<AdjacentMetadata>> {
+    let mut properties = HashMap::new();
+    let mut attributes = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+
+        let directives = match line.strip_prefix("--!rojo") {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest,
+            // Other comments are allowed in the header, but the directives end
+            // at the first line of code.
+            _ if line.is_empty() || line.starts_with("--") => continue,
+            _ => break,
+        };
+
+        let error =
+            |message: &str| format_err!("{} on line {} of {}", message, index + 1, path.display());
+
+        for token in split_directives(directives).map_err(&error)? {
+            let (key, value) = token
+                .split_once('=')
+                .ok_or_else(|| error(&format!("Directive '{}' is missing a value", token)))?;
+
+            if key.is_empty() {
+                return Err(error(&format!("Directive '{}' is missing a name", token)));
+            }
+
+            if key.eq_ignore_ascii_case("tags") {
+                let tags: Vec<_> = value
+                    .split(',')
+                    .filter(|tag| !tag.is_empty())
+                    .map(serde_json::Value::from)
+                    .collect();
+                properties.insert("Tags".to_owned(), directive_value(tags.into())?);
+            } else if let Some(attribute) = key.strip_prefix("attribute:") {
+                attributes.insert(attribute.to_owned(), parse_directive_value(value)?);
+            } else {
+                let mut chars = key.chars();
+                let first = chars.next().unwrap().to_ascii_uppercase();
+                let property = first.to_string() + chars.as_str();
+                properties.insert(property, parse_directive_value(value)?);
+            }
+        }
+    }
+
+    if properties.is_empty() && attributes.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(AdjacentMetadata::from_values(
+        properties,
+        attributes,
+        path.to_path_buf(),
+    )))
+}
+
+/// Splits a line of directives on whitespace, keeping quoted strings intact.
+fn split_directives(line: &str) -> Result<Vec<&str>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices() {
+        if in_quotes {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_quotes = false,
+                _ => {}
+            }
+        } else if c.is_whitespace() {
+            if let Some(start) = start.take() {
+                tokens.push(&line[start..index]);
+            }
+        } else {
+            start.get_or_insert(index);
+            in_quotes = c == '"';
+        }
+    }
+
+    if in_quotes {
+        return Err("Unterminated string in directive");
+    }
+
+    if let Some(start) = start {
+        tokens.push(&line[start..]);
+    }
+
+    Ok(tokens)
+}
+
+fn parse_directive_value(value: &str) -> anyhow::Result<UnresolvedValue> {
+    let value = serde_json::from_str(value).unwrap_or_else(|_| value.into());
+    directive_value(value)
+}
+
+fn directive_value(value: serde_json::Value) -> anyhow::Result<UnresolvedValue> {
+    Ok(serde_json::from_value(value)?)
+}
+
 /// Attempts to snapshot an 'init' Lua script contained inside of a folder with
 /// the given name.
 ///
@@ -106,7 +253,7 @@ pub fn snapshot_lua_init(
     init_snapshot.metadata = dir_snapshot.metadata;
 
     if let Some(mut meta) = dir_meta(vfs, folder_path)? {
-        meta.apply_all(&mut init_snapshot)?;
+        meta.apply_all(vfs, &mut init_snapshot)?;
     }
 
     Ok(Some(init_snapshot))
@@ -490,4 +637,103 @@ mod test {
             insta::assert_yaml_snapshot!(instance_snapshot);
         });
     }
+
+    #[test]
+    fn script_directives() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.server.lua",
+            VfsSnapshot::file(
+                "--!strict\n\
+                 --!rojo disabled=true tags=Enemy,Boss attribute:Health=100\n\
+                 -- A regular comment.\n\
+                 --!rojo attribute:Title=\"The Big Boss\" runContext=Client\n\
+                 print(\"Hello there!\")\n\
+                 --!rojo attribute:Ignored=true\n",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::with_emit_legacy_scripts(Some(false)),
+            &vfs,
+            Path::new("/foo.server.lua"),
+            "foo",
+            ScriptType::Server,
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::with_settings!({ sort_maps => true }, {
+            insta::assert_yaml_snapshot!(instance_snapshot);
+        });
+    }
+
+    #[test]
+    fn script_directives_with_meta() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.server.lua",
+            VfsSnapshot::file(
+                "--!rojo disabled=true attribute:Health=100 attribute:Kind=Boss\n\
+                 print(\"Hello there!\")\n",
+            ),
+        )
+        .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(
+                r#"
+                    {
+                        "properties": {
+                            "Disabled": false
+                        },
+                        "attributes": {
+                            "Health": 50
+                        }
+                    }
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_lua(
+            &InstanceContext::with_emit_legacy_scripts(Some(false)),
+            &vfs,
+            Path::new("/foo.server.lua"),
+            "foo",
+            ScriptType::Server,
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::with_settings!({ sort_maps => true }, {
+            insta::assert_yaml_snapshot!(instance_snapshot);
+        });
+    }
+
+    #[test]
+    fn invalid_script_directives() {
+        let path = Path::new("/foo.lua");
+
+        assert!(parse_directives("--!rojoish\nprint(1)", path)
+            .unwrap()
+            .is_none());
+
+        let error = parse_directives("--!strict\n--!rojo disabled", path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Directive 'disabled' is missing a value on line 2 of /foo.lua"
+        );
+
+        let error = parse_directives("--!rojo attribute:Title=\"Oops", path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unterminated string in directive on line 1 of /foo.lua"
+        );
+    }
 }
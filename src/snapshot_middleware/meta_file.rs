@@ -1,10 +1,15 @@
 use std::{borrow::Cow, collections::HashMap, path::PathBuf};
 
 use anyhow::{format_err, Context};
-use rbx_dom_weak::types::Attributes;
+use memofs::Vfs;
+use rbx_dom_weak::types::{Attributes, Variant};
 use serde::{Deserialize, Serialize};
 
-use crate::{resolution::UnresolvedValue, snapshot::InstanceSnapshot, RojoRef};
+use crate::{
+    resolution::UnresolvedValue,
+    snapshot::{DataModuleOptions, IgnoreUnknownInstances, InstanceSnapshot},
+    RojoRef,
+};
 
 /// Represents metadata in a sibling file with the same basename.
 ///
@@ -20,7 +25,22 @@ pub struct AdjacentMetadata {
     pub id: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ignore_unknown_instances: Option<bool>,
+    pub name: Option<String>,
+
+    /// The class of the container created for model files with more than one
+    /// top-level instance. Model middleware takes this field before calling
+    /// `apply_all`; it is an error anywhere else.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+
+    /// Overrides the project's `dataModules` options for a data file. Data
+    /// file middleware takes this field before calling `apply_all`; it is an
+    /// error anywhere else.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_modules: Option<DataModuleOptions>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_unknown_instances: Option<IgnoreUnknownInstances>,
 
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub properties: HashMap<String, UnresolvedValue>,
@@ -45,16 +65,62 @@ impl AdjacentMetadata {
         Ok(meta)
     }
 
+    /// Creates metadata that only sets properties and attributes, for
+    /// sources other than meta files, like directives in scripts. The path is
+    /// used for resolving file values and for error messages.
+    pub fn from_values(
+        properties: HashMap<String, UnresolvedValue>,
+        attributes: HashMap<String, UnresolvedValue>,
+        path: PathBuf,
+    ) -> Self {
+        Self {
+            schema: None,
+            id: None,
+            name: None,
+            class_name: None,
+            data_modules: None,
+            ignore_unknown_instances: None,
+            properties,
+            attributes,
+            path,
+        }
+    }
+
+    /// Overrides the name of the instance, which is otherwise taken from the
+    /// file name. This allows names that can't be used for files.
+    pub fn apply_name(&mut self, snapshot: &mut InstanceSnapshot) {
+        if let Some(name) = self.name.take() {
+            snapshot.name = Cow::Owned(name);
+        }
+    }
+
     pub fn apply_ignore_unknown_instances(&mut self, snapshot: &mut InstanceSnapshot) {
         if let Some(ignore) = self.ignore_unknown_instances.take() {
             snapshot.metadata.ignore_unknown_instances = ignore;
         }
     }
 
-    pub fn apply_properties(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+    pub fn apply_properties(
+        &mut self,
+        vfs: &Vfs,
+        snapshot: &mut InstanceSnapshot,
+    ) -> anyhow::Result<()> {
         let path = &self.path;
+        let base_path = path.parent().unwrap();
+
+        for (key, mut unresolved) in self.properties.drain() {
+            if let Some(file_path) = unresolved.load_file(vfs, base_path)? {
+                snapshot.metadata.relevant_paths.push(file_path);
+            }
+
+            let ref_path = unresolved
+                .ref_path(&snapshot.class_name, &key)
+                .with_context(|| format!("error applying meta file {}", path.display()))?;
+            if let Some(ref_path) = ref_path {
+                snapshot.metadata.ref_paths.insert(key, ref_path);
+                continue;
+            }
 
-        for (key, unresolved) in self.properties.drain() {
             let value = unresolved
                 .resolve(&snapshot.class_name, &key)
                 .with_context(|| format!("error applying meta file {}", path.display()))?;
@@ -63,9 +129,18 @@ impl AdjacentMetadata {
         }
 
         if !self.attributes.is_empty() {
-            let mut attributes = Attributes::new();
+            // Attributes set by the middleware are kept unless they're
+            // overridden here.
+            let mut attributes = match snapshot.properties.remove("Attributes") {
+                Some(Variant::Attributes(attributes)) => attributes,
+                _ => Attributes::new(),
+            };
+
+            for (key, mut unresolved) in self.attributes.drain() {
+                if let Some(file_path) = unresolved.load_file(vfs, base_path)? {
+                    snapshot.metadata.relevant_paths.push(file_path);
+                }
 
-            for (key, unresolved) in self.attributes.drain() {
                 let value = unresolved.resolve_unambiguous()?;
                 attributes.insert(key, value);
             }
@@ -89,9 +164,26 @@ impl AdjacentMetadata {
         Ok(())
     }
 
-    pub fn apply_all(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+    pub fn apply_all(&mut self, vfs: &Vfs, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+        if self.class_name.is_some() {
+            anyhow::bail!(
+                "className in a .meta.json file can only be specified for model \
+                 files with more than one top-level instance: {}",
+                self.path.display()
+            );
+        }
+
+        if self.data_modules.is_some() {
+            anyhow::bail!(
+                "dataModules in a .meta.json file can only be specified for \
+                 JSON, TOML, and YAML files: {}",
+                self.path.display()
+            );
+        }
+
+        self.apply_name(snapshot);
         self.apply_ignore_unknown_instances(snapshot);
-        self.apply_properties(snapshot)?;
+        self.apply_properties(vfs, snapshot)?;
         self.apply_id(snapshot)?;
         Ok(())
     }
@@ -114,7 +206,10 @@ pub struct DirectoryMetadata {
     pub id: Option<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ignore_unknown_instances: Option<bool>,
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_unknown_instances: Option<IgnoreUnknownInstances>,
 
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub properties: HashMap<String, UnresolvedValue>,
@@ -125,6 +220,18 @@ pub struct DirectoryMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub class_name: Option<String>,
 
+    /// Overrides `emitLegacyScripts` for this directory and everything in it.
+    /// This is read before the directory is snapshotted, so it isn't applied
+    /// by `apply_all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emit_legacy_scripts: Option<bool>,
+
+    /// Overrides the project's `dataModules` options for this directory and
+    /// everything in it. Like `emit_legacy_scripts`, this isn't applied by
+    /// `apply_all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_modules: Option<DataModuleOptions>,
+
     #[serde(skip)]
     pub path: PathBuf,
 }
@@ -142,10 +249,11 @@ impl DirectoryMetadata {
         Ok(meta)
     }
 
-    pub fn apply_all(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+    pub fn apply_all(&mut self, vfs: &Vfs, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+        self.apply_name(snapshot);
         self.apply_ignore_unknown_instances(snapshot);
         self.apply_class_name(snapshot)?;
-        self.apply_properties(snapshot)?;
+        self.apply_properties(vfs, snapshot)?;
         self.apply_id(snapshot)?;
 
         Ok(())
@@ -167,16 +275,39 @@ impl DirectoryMetadata {
         Ok(())
     }
 
+    fn apply_name(&mut self, snapshot: &mut InstanceSnapshot) {
+        if let Some(name) = self.name.take() {
+            snapshot.name = Cow::Owned(name);
+        }
+    }
+
     fn apply_ignore_unknown_instances(&mut self, snapshot: &mut InstanceSnapshot) {
         if let Some(ignore) = self.ignore_unknown_instances.take() {
             snapshot.metadata.ignore_unknown_instances = ignore;
         }
     }
 
-    fn apply_properties(&mut self, snapshot: &mut InstanceSnapshot) -> anyhow::Result<()> {
+    fn apply_properties(
+        &mut self,
+        vfs: &Vfs,
+        snapshot: &mut InstanceSnapshot,
+    ) -> anyhow::Result<()> {
         let path = &self.path;
+        let base_path = path.parent().unwrap();
+
+        for (key, mut unresolved) in self.properties.drain() {
+            if let Some(file_path) = unresolved.load_file(vfs, base_path)? {
+                snapshot.metadata.relevant_paths.push(file_path);
+            }
+
+            let ref_path = unresolved
+                .ref_path(&snapshot.class_name, &key)
+                .with_context(|| format!("error applying meta file {}", path.display()))?;
+            if let Some(ref_path) = ref_path {
+                snapshot.metadata.ref_paths.insert(key, ref_path);
+                continue;
+            }
 
-        for (key, unresolved) in self.properties.drain() {
             let value = unresolved
                 .resolve(&snapshot.class_name, &key)
                 .with_context(|| format!("error applying meta file {}", path.display()))?;
@@ -185,9 +316,18 @@ impl DirectoryMetadata {
         }
 
         if !self.attributes.is_empty() {
-            let mut attributes = Attributes::new();
+            // Attributes set by the middleware are kept unless they're
+            // overridden here.
+            let mut attributes = match snapshot.properties.remove("Attributes") {
+                Some(Variant::Attributes(attributes)) => attributes,
+                _ => Attributes::new(),
+            };
+
+            for (key, mut unresolved) in self.attributes.drain() {
+                if let Some(file_path) = unresolved.load_file(vfs, base_path)? {
+                    snapshot.metadata.relevant_paths.push(file_path);
+                }
 
-            for (key, unresolved) in self.attributes.drain() {
                 let value = unresolved.resolve_unambiguous()?;
                 attributes.insert(key, value);
             }
@@ -5,45 +5,65 @@
 
 #![allow(dead_code)]
 
+mod command;
 mod csv;
+mod data_module;
 mod dir;
 mod json;
 mod json_model;
+mod localization;
+mod localization_json;
 mod lua;
 mod meta_file;
+mod po;
 mod project;
+mod rbxl;
+mod rbxlx;
 mod rbxm;
 mod rbxmx;
 mod toml;
 mod txt;
 mod util;
+mod yaml;
 
 use std::{
+    borrow::Cow,
+    collections::BTreeMap,
     path::{Path, PathBuf},
     sync::OnceLock,
 };
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::types::{Variant, VariantType};
+use rbx_reflection::DataType;
 use serde::{Deserialize, Serialize};
 
-use crate::glob::Glob;
 use crate::snapshot::{InstanceContext, InstanceSnapshot, SyncRule};
+use crate::{glob::Glob, reflection::find_property_descriptor};
 
 use self::{
+    command::snapshot_command,
     csv::{snapshot_csv, snapshot_csv_init},
-    dir::snapshot_dir,
+    dir::{dir_context, snapshot_dir},
     json::snapshot_json,
-    json_model::snapshot_json_model,
+    json_model::{snapshot_json_model, snapshot_toml_model, snapshot_yaml_model},
+    localization_json::snapshot_localization_json,
     lua::{snapshot_lua, snapshot_lua_init, ScriptType},
+    po::{snapshot_po, snapshot_po_init},
     project::snapshot_project,
     rbxm::snapshot_rbxm,
     rbxmx::snapshot_rbxmx,
     toml::snapshot_toml,
     txt::snapshot_txt,
+    util::instance_name_from_file_name,
+    yaml::snapshot_yaml,
 };
 
-pub use self::{project::snapshot_project_node, util::emit_legacy_scripts_default};
+pub use self::{
+    command::CommandOutputCache, csv::validate_localization_csv, project::snapshot_project_node,
+    util::emit_legacy_scripts_default,
+};
 
 /// Returns an `InstanceSnapshot` for the provided path.
 /// This will inspect the path and find the appropriate middleware for it,
@@ -60,43 +80,24 @@ pub fn snapshot_from_vfs(
         None => return Ok(None),
     };
 
-    if meta.is_dir() {
-        if let Some(init_path) = get_init_path(vfs, path)? {
-            // TODO: support user-defined init paths
-            // If and when we do, make sure to go support it in
-            // `Project::set_file_name`, as right now it special-cases
-            // `default.project.json` as an `init` path.
-            for rule in default_sync_rules() {
-                if rule.matches(&init_path) {
-                    return match rule.middleware {
-                        Middleware::Project => {
-                            let name = init_path
-                                .parent()
-                                .and_then(Path::file_name)
-                                .and_then(|s| s.to_str()).expect("default.project.json should be inside a folder with a unicode name");
-                            snapshot_project(context, vfs, &init_path, name)
-                        }
-
-                        Middleware::ModuleScript => {
-                            snapshot_lua_init(context, vfs, &init_path, ScriptType::Module)
-                        }
-                        Middleware::ServerScript => {
-                            snapshot_lua_init(context, vfs, &init_path, ScriptType::Server)
-                        }
-                        Middleware::ClientScript => {
-                            snapshot_lua_init(context, vfs, &init_path, ScriptType::Client)
-                        }
-
-                        Middleware::Csv => snapshot_csv_init(context, vfs, &init_path),
-
-                        _ => snapshot_dir(context, vfs, path),
-                    };
-                }
-            }
-            snapshot_dir(context, vfs, path)
-        } else {
-            snapshot_dir(context, vfs, path)
+    let (middleware, snapshot) = if meta.is_dir() {
+        let dir_context = dir_context(context, vfs, path)?;
+
+        let (middleware, mut snapshot) = match get_init_path(&dir_context, vfs, path)? {
+            Some((init_path, middleware)) => (
+                Some(middleware),
+                middleware.snapshot_init(&dir_context, vfs, path, &init_path)?,
+            ),
+            None => (None, snapshot_dir(&dir_context, vfs, path)?),
+        };
+
+        // The directory itself keeps the context it was given, so that when
+        // it's snapshotted again, changes to its meta file are picked up.
+        if let (Cow::Owned(_), Some(snapshot)) = (&dir_context, &mut snapshot) {
+            snapshot.metadata.context = context.clone();
         }
+
+        (middleware, snapshot)
     } else {
         let file_name = path
             .file_name()
@@ -110,83 +111,331 @@ pub fn snapshot_from_vfs(
             _ => {}
         }
 
-        snapshot_from_path(context, vfs, path)
+        // User-defined init files are consumed by the directory containing
+        // them, so they should not also turn into children of it.
+        if context
+            .init_file_rules
+            .iter()
+            .any(|rule| rule.name == file_name)
+        {
+            return Ok(None);
+        }
+
+        match snapshot_from_path(context, vfs, path)? {
+            Some((middleware, snapshot)) => (Some(middleware), snapshot),
+            None => return Ok(None),
+        }
+    };
+
+    let mut snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => return Ok(None),
+    };
+
+    // Projects apply their own class defaults and do their own validation for
+    // every node. Otherwise, the children of a directory have already been
+    // through this function, but the descendants of a file (like a model)
+    // have not.
+    if middleware != Some(Middleware::Project) {
+        apply_class_defaults(context, &mut snapshot, !meta.is_dir());
+
+        resolve_assets(context, &mut snapshot, !meta.is_dir())
+            .with_context(|| format!("Could not resolve assets in {}", path.display()))?;
+
+        if context.strict {
+            validate_snapshot(&snapshot, !meta.is_dir())
+                .with_context(|| format!("Invalid instance in {}", path.display()))?;
+        }
     }
+
+    Ok(Some(snapshot))
 }
 
-/// Gets an `init` path for the given directory.
-/// This uses an intrinsic priority list and for compatibility,
-/// it should not be changed.
-fn get_init_path<P: AsRef<Path>>(vfs: &Vfs, dir: P) -> anyhow::Result<Option<PathBuf>> {
-    let path = dir.as_ref();
+/// Fills in any properties missing from the snapshot using the default
+/// properties for its class and superclasses, preferring the most specific
+/// class. If `recursive` is set, this is done for all descendants as well.
+fn apply_class_defaults(
+    context: &InstanceContext,
+    snapshot: &mut InstanceSnapshot,
+    recursive: bool,
+) {
+    if context.class_defaults.is_empty() {
+        return;
+    }
+
+    let database = crate::reflection::database();
+    let mut current_class = Some(snapshot.class_name.as_ref());
+
+    while let Some(class_name) = current_class {
+        if let Some(defaults) = context.class_defaults.get(class_name) {
+            for (key, value) in defaults {
+                if !snapshot.properties.contains_key(key) {
+                    snapshot.properties.insert(key.clone(), value.clone());
+                }
+            }
+        }
 
-    let project_path = path.join("default.project.json");
-    if vfs.metadata(&project_path).with_not_found()?.is_some() {
-        return Ok(Some(project_path));
+        current_class = database
+            .classes
+            .get(class_name)
+            .and_then(|class| class.superclass.as_deref());
     }
 
-    let init_path = path.join("init.luau");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+    if recursive {
+        for child in &mut snapshot.children {
+            apply_class_defaults(context, child, true);
+        }
     }
+}
+
+/// Replaces `@asset/` references in `Content` properties with the content URL
+/// of the asset they refer to. If `recursive` is set, this is done for all
+/// descendants as well.
+fn resolve_assets(
+    context: &InstanceContext,
+    snapshot: &mut InstanceSnapshot,
+    recursive: bool,
+) -> anyhow::Result<()> {
+    for (key, value) in &mut snapshot.properties {
+        if let Variant::Content(content) = value {
+            let resolved = context
+                .resolve_asset(content.as_ref())
+                .with_context(|| format!("Invalid {} property on {}", key, snapshot.name))?;
 
-    let init_path = path.join("init.lua");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+            if let Some(resolved) = resolved {
+                *value = Variant::Content(resolved.into());
+            }
+        }
     }
 
-    let init_path = path.join("init.server.luau");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+    if recursive {
+        for child in &mut snapshot.children {
+            resolve_assets(context, child, true)?;
+        }
     }
 
-    let init_path = path.join("init.server.lua");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+    Ok(())
+}
+
+/// Checks that the snapshot's class and properties are known to the
+/// reflection database and that every property has the type the database
+/// expects. If `recursive` is set, all descendants are checked as well.
+fn validate_snapshot(snapshot: &InstanceSnapshot, recursive: bool) -> anyhow::Result<()> {
+    let class_name = snapshot.class_name.as_ref();
+
+    if !crate::reflection::database()
+        .classes
+        .contains_key(class_name)
+    {
+        bail!("{} has unknown class {}", snapshot.name, class_name);
     }
 
-    let init_path = path.join("init.client.luau");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+    for (prop_name, value) in &snapshot.properties {
+        let descriptor = find_property_descriptor(class_name, prop_name).with_context(|| {
+            format!(
+                "{} has unknown property {}.{}",
+                snapshot.name, class_name, prop_name
+            )
+        })?;
+
+        let valid = match &descriptor.data_type {
+            DataType::Value(expected) => is_compatible_type(*expected, value.ty()),
+            DataType::Enum(_) => value.ty() == VariantType::Enum,
+            _ => true,
+        };
+
+        if !valid {
+            bail!(
+                "{} has property {}.{} of type {:?}, but it should be {}",
+                snapshot.name,
+                class_name,
+                prop_name,
+                value.ty(),
+                describe_data_type(&descriptor.data_type)
+            );
+        }
     }
 
-    let init_path = path.join("init.client.lua");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+    if recursive {
+        for child in &snapshot.children {
+            validate_snapshot(child, true)?;
+        }
     }
 
-    let init_path = path.join("init.csv");
-    if vfs.metadata(&init_path).with_not_found()?.is_some() {
-        return Ok(Some(init_path));
+    Ok(())
+}
+
+/// Tells whether a value of type `actual` can be used for a property of type
+/// `expected`. Besides exact matches, this allows the alternate forms that
+/// Roblox's file formats use for some types.
+fn is_compatible_type(expected: VariantType, actual: VariantType) -> bool {
+    use VariantType::*;
+
+    matches!(
+        (expected, actual),
+        (Color3, Color3uint8) | (Color3uint8, Color3) | (Content, String)
+    ) || expected == actual
+}
+
+fn describe_data_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Value(ty) => format!("{:?}", ty),
+        DataType::Enum(name) => format!("Enum.{}", name),
+        _ => "unknown".to_owned(),
+    }
+}
+
+/// The file names that Rojo treats as the `init` file of a directory, along
+/// with the middleware used for them. This is an intrinsic priority list and
+/// for compatibility, it should not be changed.
+const DEFAULT_INIT_FILES: &[(&str, Middleware)] = &[
+    ("default.project.json", Middleware::Project),
+    ("init.luau", Middleware::ModuleScript),
+    ("init.lua", Middleware::ModuleScript),
+    ("init.server.luau", Middleware::ServerScript),
+    ("init.server.lua", Middleware::ServerScript),
+    ("init.client.luau", Middleware::ClientScript),
+    ("init.client.lua", Middleware::ClientScript),
+    ("init.csv", Middleware::Csv),
+    ("init.pot", Middleware::Po),
+];
+
+/// Gets an `init` path for the given directory, along with the middleware that
+/// should be used to snapshot it.
+///
+/// User-defined init files from the context are checked first, in the order
+/// they were specified, followed by Rojo's default init files.
+fn get_init_path<P: AsRef<Path>>(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    dir: P,
+) -> anyhow::Result<Option<(PathBuf, Middleware)>> {
+    let path = dir.as_ref();
+
+    let user_init_files = context
+        .init_file_rules
+        .iter()
+        .map(|rule| (rule.name.as_str(), rule.middleware));
+    let default_init_files = DEFAULT_INIT_FILES.iter().copied();
+
+    for (file_name, middleware) in user_init_files.chain(default_init_files) {
+        let init_path = path.join(file_name);
+        if vfs.metadata(&init_path).with_not_found()?.is_some() {
+            return Ok(Some((init_path, middleware)));
+        }
     }
 
     Ok(None)
 }
 
 /// Gets a snapshot for a path given an InstanceContext and Vfs, taking
-/// user specified sync rules into account.
+/// user specified sync rules into account. The middleware that was used is
+/// returned alongside the snapshot.
 fn snapshot_from_path(
     context: &InstanceContext,
     vfs: &Vfs,
     path: &Path,
-) -> anyhow::Result<Option<InstanceSnapshot>> {
-    if let Some(rule) = context.get_user_sync_rule(path) {
-        return rule
-            .middleware
-            .snapshot(context, vfs, path, rule.file_name_for_path(path)?);
-    } else {
-        for rule in default_sync_rules() {
-            if rule.matches(path) {
-                return rule.middleware.snapshot(
-                    context,
-                    vfs,
-                    path,
-                    rule.file_name_for_path(path)?,
+) -> anyhow::Result<Option<(Middleware, Option<InstanceSnapshot>)>> {
+    let rule = match context
+        .get_user_sync_rule(path)
+        .or_else(|| default_sync_rules().iter().find(|rule| rule.matches(path)))
+    {
+        Some(rule) => rule,
+        None => return Ok(None),
+    };
+
+    let name = instance_name_from_file_name(context, rule.file_name_for_path(path)?);
+
+    if rule.class_name.is_none() && rule.properties.is_empty() {
+        let snapshot = rule.middleware.snapshot(context, vfs, path, &name, rule)?;
+        return Ok(Some((rule.middleware, snapshot)));
+    }
+
+    let mut context = context.clone();
+    context.set_pending_sync_rule(path, rule);
+
+    let mut snapshot = rule.middleware.snapshot(&context, vfs, path, &name, rule)?;
+
+    if !rule.middleware.applies_sync_rules() {
+        if let Some(snapshot) = &mut snapshot {
+            apply_sync_rule(&context, vfs, path, snapshot)?;
+        }
+    }
+
+    Ok(Some((rule.middleware, snapshot)))
+}
+
+/// Gives the snapshot of the file at `path` the class name and properties of
+/// the sync rule that picked its middleware, if the rule sets any. Middleware
+/// that read meta files call this before applying them, so that meta files
+/// are resolved against the rule's class and can override its properties.
+fn apply_sync_rule(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    snapshot: &mut InstanceSnapshot,
+) -> anyhow::Result<()> {
+    let rule = match context.pending_sync_rule(path) {
+        Some(rule) => rule,
+        None => return Ok(()),
+    };
+
+    apply_sync_rule_values(rule, vfs, snapshot).with_context(|| {
+        format!(
+            "Could not apply sync rule '{}' to {}",
+            rule.include.as_str(),
+            path.display()
+        )
+    })
+}
+
+fn apply_sync_rule_values(
+    rule: &SyncRule,
+    vfs: &Vfs,
+    snapshot: &mut InstanceSnapshot,
+) -> anyhow::Result<()> {
+    if let Some(class_name) = &rule.class_name {
+        // Properties from the middleware that the new class doesn't have,
+        // like the Source of a script that becomes a Model, are dropped.
+        snapshot.properties.retain(|key, _| {
+            let keep = find_property_descriptor(class_name, key).is_some();
+            if !keep {
+                log::debug!(
+                    "Dropping property {} from {} because {} does not have it",
+                    key,
+                    snapshot.name,
+                    class_name
                 );
             }
+            keep
+        });
+
+        snapshot.class_name = Cow::Owned(class_name.clone());
+    }
+
+    for (key, unresolved) in &rule.properties {
+        let class_name = snapshot.class_name.as_ref();
+
+        if find_property_descriptor(class_name, key).is_none() {
+            bail!("Unknown property {}.{}", class_name, key);
         }
+
+        let mut unresolved = unresolved.clone();
+
+        if let Some(file_path) = unresolved.load_file(vfs, &rule.base_path)? {
+            snapshot.metadata.relevant_paths.push(file_path);
+        }
+
+        if let Some(ref_path) = unresolved.ref_path(class_name, key)? {
+            snapshot.metadata.ref_paths.insert(key.clone(), ref_path);
+            continue;
+        }
+
+        let value = unresolved.resolve(class_name, key)?;
+        snapshot.properties.insert(key.clone(), value);
     }
-    Ok(None)
+
+    Ok(())
 }
 
 /// Represents a possible 'transformer' used by Rojo to turn a file system
@@ -197,7 +446,11 @@ fn snapshot_from_path(
 #[serde(rename_all = "camelCase")]
 pub enum Middleware {
     Csv,
+    LocalizationJson,
+    Po,
     JsonModel,
+    YamlModel,
+    TomlModel,
     Json,
     ServerScript,
     ClientScript,
@@ -206,23 +459,31 @@ pub enum Middleware {
     Rbxm,
     Rbxmx,
     Toml,
+    Yaml,
     Text,
+    Command,
     Ignore,
 }
 
 impl Middleware {
     /// Creates a snapshot for the given path from the Middleware with
-    /// the provided name.
+    /// the provided name. Some middleware, like `Command`, are configured by
+    /// the sync rule that chose them.
     fn snapshot(
         &self,
         context: &InstanceContext,
         vfs: &Vfs,
         path: &Path,
         name: &str,
+        rule: &SyncRule,
     ) -> anyhow::Result<Option<InstanceSnapshot>> {
         match self {
             Self::Csv => snapshot_csv(context, vfs, path, name),
+            Self::LocalizationJson => snapshot_localization_json(context, vfs, path, name),
+            Self::Po => snapshot_po(context, vfs, path, name),
             Self::JsonModel => snapshot_json_model(context, vfs, path, name),
+            Self::YamlModel => snapshot_yaml_model(context, vfs, path, name),
+            Self::TomlModel => snapshot_toml_model(context, vfs, path, name),
             Self::Json => snapshot_json(context, vfs, path, name),
             Self::ServerScript => snapshot_lua(context, vfs, path, name, ScriptType::Server),
             Self::ClientScript => snapshot_lua(context, vfs, path, name, ScriptType::Client),
@@ -231,10 +492,70 @@ impl Middleware {
             Self::Rbxm => snapshot_rbxm(context, vfs, path, name),
             Self::Rbxmx => snapshot_rbxmx(context, vfs, path, name),
             Self::Toml => snapshot_toml(context, vfs, path, name),
+            Self::Yaml => snapshot_yaml(context, vfs, path, name),
             Self::Text => snapshot_txt(context, vfs, path, name),
+            Self::Command => snapshot_command(context, vfs, path, name, rule),
             Self::Ignore => Ok(None),
         }
     }
+
+    /// Whether this middleware applies the class name and properties of the
+    /// sync rule that chose it by itself, which it does before applying meta
+    /// files. The rule is applied to the output of other middleware instead.
+    fn applies_sync_rules(&self) -> bool {
+        matches!(
+            self,
+            Self::Csv
+                | Self::LocalizationJson
+                | Self::Po
+                | Self::Json
+                | Self::ServerScript
+                | Self::ClientScript
+                | Self::ModuleScript
+                | Self::Rbxm
+                | Self::Rbxmx
+                | Self::Toml
+                | Self::Yaml
+                | Self::Text
+                | Self::Command
+        )
+    }
+
+    /// Creates a snapshot for the directory at `dir_path` using `init_path`
+    /// as its `init` file.
+    fn snapshot_init(
+        &self,
+        context: &InstanceContext,
+        vfs: &Vfs,
+        dir_path: &Path,
+        init_path: &Path,
+    ) -> anyhow::Result<Option<InstanceSnapshot>> {
+        match self {
+            Self::Project => {
+                let name = dir_path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .with_context(|| {
+                        format!(
+                            "{} should be inside a folder with a unicode name",
+                            init_path.display()
+                        )
+                    })?;
+                let name = instance_name_from_file_name(context, name);
+                snapshot_project(context, vfs, init_path, &name)
+            }
+            Self::ModuleScript => snapshot_lua_init(context, vfs, init_path, ScriptType::Module),
+            Self::ServerScript => snapshot_lua_init(context, vfs, init_path, ScriptType::Server),
+            Self::ClientScript => snapshot_lua_init(context, vfs, init_path, ScriptType::Client),
+            Self::Csv => snapshot_csv_init(context, vfs, init_path),
+            Self::Po => snapshot_po_init(context, vfs, init_path),
+            _ => anyhow::bail!(
+                "The {:?} middleware cannot be used for init files, but was used for {}",
+                self,
+                init_path.display()
+            ),
+        }
+    }
 }
 
 /// A helper for easily defining a SyncRule. Arguments are passed literally
@@ -251,6 +572,9 @@ macro_rules! sync_rule {
             include: Glob::new($pattern).unwrap(),
             exclude: None,
             suffix: None,
+            class_name: None,
+            properties: BTreeMap::new(),
+            command: Vec::new(),
             base_path: PathBuf::new(),
         }
     };
@@ -260,6 +584,9 @@ macro_rules! sync_rule {
             include: Glob::new($pattern).unwrap(),
             exclude: None,
             suffix: Some($suffix.into()),
+            class_name: None,
+            properties: BTreeMap::new(),
+            command: Vec::new(),
             base_path: PathBuf::new(),
         }
     };
@@ -269,6 +596,9 @@ macro_rules! sync_rule {
             include: Glob::new($pattern).unwrap(),
             exclude: Some(Glob::new($exclude).unwrap()),
             suffix: Some($suffix.into()),
+            class_name: None,
+            properties: BTreeMap::new(),
+            command: Vec::new(),
             base_path: PathBuf::new(),
         }
     };
@@ -289,8 +619,17 @@ pub fn default_sync_rules() -> &'static [SyncRule] {
             sync_rule!("*.{lua,luau}", ModuleScript),
             sync_rule!("*.project.json", Project, ".project.json"),
             sync_rule!("*.model.json", JsonModel, ".model.json"),
+            sync_rule!("*.model.yaml", YamlModel, ".model.yaml"),
+            sync_rule!("*.model.yml", YamlModel, ".model.yml"),
+            sync_rule!("*.model.toml", TomlModel, ".model.toml"),
+            sync_rule!(
+                "*.localization.json",
+                LocalizationJson,
+                ".localization.json"
+            ),
             sync_rule!("*.json", Json, ".json", "*.meta.json"),
             sync_rule!("*.toml", Toml),
+            sync_rule!("*.{yaml,yml}", Yaml),
             sync_rule!("*.csv", Csv),
             sync_rule!("*.txt", Text),
             sync_rule!("*.rbxmx", Rbxmx),
@@ -0,0 +1,525 @@
+//! Turns gettext PO files into LocalizationTables.
+//!
+//! Each message's `msgid` becomes the entry's source string and its `msgctxt`
+//! becomes the entry's context. Extracted comments (`#.`) become the entry's
+//! example. Translations are stored under the locale from the file's
+//! `Language` header, or the file's name if there isn't one.
+
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    path::Path,
+    str,
+};
+
+use anyhow::{bail, Context};
+use memofs::Vfs;
+
+use crate::snapshot::{InstanceContext, InstanceSnapshot};
+
+use super::{
+    dir::{dir_meta, snapshot_dir_no_meta},
+    localization::{encode_localization_entries, snapshot_localization_table, LocalizationEntry},
+};
+
+/// Snapshots a single PO or POT file as a LocalizationTable.
+pub fn snapshot_po(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let file = read_po_file(vfs, path)?;
+    let table_contents = encode_localization_entries(&convert_po_files(&[file]));
+
+    Ok(Some(snapshot_localization_table(
+        context,
+        vfs,
+        path,
+        name,
+        table_contents,
+    )?))
+}
+
+/// Snapshots a directory containing an `init.pot` file as a LocalizationTable.
+/// Messages come from `init.pot`, and translations come from every `.po` file
+/// in the directory.
+pub fn snapshot_po_init(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    init_path: &Path,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let folder_path = init_path.parent().unwrap();
+    let mut snapshot = snapshot_dir_no_meta(context, vfs, folder_path)?.unwrap();
+
+    if snapshot.class_name != "Folder" {
+        anyhow::bail!(
+            "init.pot can only be used if the instance produced by \
+             the containing directory would be a Folder.\n\
+             \n\
+             The directory {} turned into an instance of class {}.",
+            folder_path.display(),
+            snapshot.class_name
+        );
+    }
+
+    let mut po_paths = Vec::new();
+    for entry in vfs.read_dir(folder_path)? {
+        let path = entry?.path().to_path_buf();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("po") {
+            po_paths.push(path);
+        }
+    }
+    po_paths.sort();
+
+    let mut files = vec![read_po_file(vfs, init_path)?];
+    for path in &po_paths {
+        files.push(read_po_file(vfs, path)?);
+    }
+
+    let table_contents = encode_localization_entries(&convert_po_files(&files));
+
+    snapshot.class_name = Cow::Borrowed("LocalizationTable");
+    snapshot
+        .properties
+        .insert("Contents".to_owned(), table_contents.into());
+    snapshot.metadata.relevant_paths.extend(po_paths);
+
+    if let Some(mut meta) = dir_meta(vfs, folder_path)? {
+        meta.apply_all(vfs, &mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Reads and parses the PO file at `path`. POT files are templates, so they
+/// don't have a locale.
+fn read_po_file(vfs: &Vfs, path: &Path) -> anyhow::Result<PoFile> {
+    let contents = vfs.read(path)?;
+
+    let mut file = str::from_utf8(&contents)
+        .map_err(anyhow::Error::from)
+        .and_then(parse_po)
+        .with_context(|| format!("File was not a valid PO file: {}", path.display()))?;
+
+    file.locale = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("pot") => None,
+        _ => file
+            .locale
+            .as_deref()
+            .or_else(|| path.file_stem().and_then(|stem| stem.to_str()))
+            .map(roblox_locale),
+    };
+
+    for line in &file.skipped_plurals {
+        log::warn!(
+            "Skipping the message with plural forms on line {} of {}, \
+             since LocalizationTables don't support plurals",
+            line,
+            path.display()
+        );
+    }
+
+    Ok(file)
+}
+
+/// Turns a gettext locale like `pt_BR` into the form Roblox uses, `pt-br`.
+fn roblox_locale(locale: &str) -> String {
+    locale.replace('_', "-").to_lowercase()
+}
+
+/// Merges the messages from all of the given files into localization entries.
+/// Messages are identified by their context and source string, and keep the
+/// order they first appear in.
+fn convert_po_files(files: &[PoFile]) -> Vec<LocalizationEntry<'_>> {
+    let mut entries: Vec<LocalizationEntry> = Vec::new();
+    let mut indices = HashMap::new();
+
+    for file in files {
+        for message in &file.messages {
+            let index = match indices.entry((message.context.as_deref(), message.id.as_str())) {
+                Entry::Occupied(occupied) => *occupied.get(),
+                Entry::Vacant(vacant) => {
+                    entries.push(LocalizationEntry {
+                        context: message.context.as_deref().map(Cow::Borrowed),
+                        source: Some(Cow::Borrowed(&message.id)),
+                        ..Default::default()
+                    });
+                    *vacant.insert(entries.len() - 1)
+                }
+            };
+            let entry = &mut entries[index];
+
+            if entry.example.is_none() && !message.comments.is_empty() {
+                entry.example = Some(Cow::Owned(message.comments.join("\n")));
+            }
+
+            // Fuzzy translations haven't been checked by a translator yet, so
+            // gettext doesn't use them either.
+            if let (Some(locale), false) = (&file.locale, message.fuzzy) {
+                entry.set_column(locale.as_str(), message.translation.as_str());
+            }
+        }
+    }
+
+    entries
+}
+
+#[derive(Debug, Default)]
+struct PoFile {
+    locale: Option<String>,
+    messages: Vec<PoMessage>,
+
+    /// The lines of messages that were skipped because they have plural
+    /// forms.
+    skipped_plurals: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+struct PoMessage {
+    context: Option<String>,
+    id: String,
+    translation: String,
+    comments: Vec<String>,
+    fuzzy: bool,
+    plural: bool,
+    line: usize,
+}
+
+/// The part of a message that a continued string is added to.
+#[derive(Debug, Clone, Copy)]
+enum PoField {
+    Context,
+    Id,
+    Translation,
+    Plural,
+}
+
+/// Parses the contents of a PO file. Messages with plural forms are skipped
+/// since LocalizationTables have no equivalent.
+fn parse_po(contents: &str) -> anyhow::Result<PoFile> {
+    let mut parser = PoParser::default();
+
+    for (index, line) in contents.lines().enumerate() {
+        parser.line = index + 1;
+        parser
+            .parse_line(line.trim())
+            .with_context(|| format!("Error on line {}", index + 1))?;
+    }
+
+    Ok(parser.finish())
+}
+
+#[derive(Debug, Default)]
+struct PoParser {
+    file: PoFile,
+    message: PoMessage,
+    field: Option<PoField>,
+    has_translation: bool,
+    line: usize,
+}
+
+impl PoParser {
+    fn parse_line(&mut self, line: &str) -> anyhow::Result<()> {
+        // A comment or keyword after a message's translation starts the next
+        // message.
+        let starts_message = line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("msgctxt")
+            || line.starts_with("msgid");
+
+        if self.has_translation && starts_message {
+            self.finish_message();
+        }
+
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(rest) = line.strip_prefix('#') {
+            if rest.starts_with('~') {
+                // Obsolete messages are kept around by gettext tools but
+                // aren't used, so the comments before them are dropped too.
+                self.message = PoMessage::default();
+            } else if let Some(comment) = rest.strip_prefix('.') {
+                self.message.comments.push(comment.trim().to_owned());
+            } else if let Some(flags) = rest.strip_prefix(',') {
+                self.message.fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            }
+
+            return Ok(());
+        }
+
+        if line.starts_with('"') {
+            let value = parse_po_string(line)?;
+            let target = match self.field {
+                Some(PoField::Context) => self.message.context.get_or_insert_with(String::new),
+                Some(PoField::Id) => &mut self.message.id,
+                Some(PoField::Translation) => &mut self.message.translation,
+                Some(PoField::Plural) => return Ok(()),
+                None => bail!("string is not part of a message"),
+            };
+            target.push_str(&value);
+
+            return Ok(());
+        }
+
+        let (keyword, value) = line
+            .split_once(char::is_whitespace)
+            .with_context(|| format!("unexpected line: {}", line))?;
+
+        match keyword {
+            "msgctxt" => {
+                self.message.context = Some(parse_po_string(value.trim())?);
+                self.field = Some(PoField::Context);
+            }
+            "msgid" => {
+                self.message.id = parse_po_string(value.trim())?;
+                self.message.line = self.line;
+                self.field = Some(PoField::Id);
+            }
+            "msgstr" => {
+                self.message.translation = parse_po_string(value.trim())?;
+                self.field = Some(PoField::Translation);
+                self.has_translation = true;
+            }
+            "msgid_plural" => {
+                self.message.plural = true;
+                self.field = Some(PoField::Plural);
+            }
+            _ if keyword.starts_with("msgstr[") => {
+                self.message.plural = true;
+                self.field = Some(PoField::Plural);
+                self.has_translation = true;
+            }
+            _ => bail!("unknown keyword {}", keyword),
+        }
+
+        Ok(())
+    }
+
+    /// Adds the current message to the file. The header is the message with
+    /// an empty ID, and is only used to find the file's locale.
+    fn finish_message(&mut self) {
+        let message = std::mem::take(&mut self.message);
+        self.field = None;
+        self.has_translation = false;
+
+        if message.plural {
+            self.file.skipped_plurals.push(message.line);
+            return;
+        }
+
+        if message.id.is_empty() && message.context.is_none() {
+            self.file.locale = message.translation.lines().find_map(|line| {
+                let language = line.strip_prefix("Language:")?.trim();
+                (!language.is_empty()).then(|| language.to_owned())
+            });
+            return;
+        }
+
+        self.file.messages.push(message);
+    }
+
+    fn finish(mut self) -> PoFile {
+        if self.has_translation {
+            self.finish_message();
+        }
+
+        self.file
+    }
+}
+
+/// Parses a quoted string from a PO file, handling C-style escapes.
+fn parse_po_string(value: &str) -> anyhow::Result<String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|_| value.len() >= 2)
+        .with_context(|| format!("expected a quoted string, got {}", value))?;
+
+    let mut output = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some('r') => output.push('\r'),
+            Some('"') => output.push('"'),
+            Some('\\') => output.push('\\'),
+            Some(other) => bail!("unknown escape sequence \\{}", other),
+            None => bail!("string ends with a backslash"),
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    use crate::snapshot_middleware::csv::snapshot_csv;
+
+    const TEMPLATE: &str = r#"
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+#. An exclamation of despair
+msgid "Ack!"
+msgstr ""
+
+msgctxt "Shop"
+msgid "Buy"
+msgstr ""
+"#;
+
+    const SPANISH: &str = r#"
+msgid ""
+msgstr ""
+"Language: es\n"
+
+#. An exclamation of despair
+msgid "Ack!"
+msgstr "¡Ay!"
+
+msgctxt "Shop"
+msgid "Buy"
+msgstr ""
+"Com"
+"prar"
+
+#, fuzzy
+msgid "Sell"
+msgstr "Vender"
+
+#~ msgid "Old"
+#~ msgstr "Viejo"
+"#;
+
+    #[test]
+    fn po_directory() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Strings",
+            VfsSnapshot::dir([
+                ("init.pot", VfsSnapshot::file(TEMPLATE)),
+                ("spanish.po", VfsSnapshot::file(SPANISH)),
+                (
+                    "fr.po",
+                    VfsSnapshot::file("msgid \"Ack!\"\nmsgstr \"Zut !\"\n"),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_po_init(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/Strings/init.pot"),
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn same_output_as_csv() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/es.po", VfsSnapshot::file(SPANISH))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let snapshot = snapshot_po(&InstanceContext::default(), &vfs, Path::new("/es.po"), "es")
+            .unwrap()
+            .unwrap();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/es.csv",
+            VfsSnapshot::file(
+                "Context,Source,Example,es\n\
+                 ,Ack!,An exclamation of despair,¡Ay!\n\
+                 Shop,Buy,,Comprar\n\
+                 ,Sell,,\n",
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let csv_snapshot = snapshot_csv(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/es.csv"),
+            "es",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            snapshot.properties.get("Contents"),
+            csv_snapshot.properties.get("Contents")
+        );
+    }
+
+    #[test]
+    fn plural_forms_are_skipped() {
+        let file = parse_po(
+            "msgid \"apple\"\n\
+             msgid_plural \"apples\"\n\
+             msgstr[0] \"manzana\"\n\
+             msgstr[1] \"\"\n\
+             \"manzanas\"\n\
+             \n\
+             msgid \"pear\"\n\
+             msgstr \"pera\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(file.skipped_plurals, vec![1]);
+        assert_eq!(file.messages.len(), 1);
+        assert_eq!(file.messages[0].id, "pear");
+        assert_eq!(file.messages[0].translation, "pera");
+    }
+
+    #[test]
+    fn locales() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/Strings",
+            VfsSnapshot::dir([
+                (
+                    "brazil.po",
+                    VfsSnapshot::file("msgid \"\"\nmsgstr \"Language: pt_BR\\n\"\n"),
+                ),
+                (
+                    "zh_Hans.po",
+                    VfsSnapshot::file("msgid \"Hi\"\nmsgstr \"\"\n"),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let brazil = read_po_file(&vfs, Path::new("/Strings/brazil.po")).unwrap();
+        assert_eq!(brazil.locale.as_deref(), Some("pt-br"));
+
+        let chinese = read_po_file(&vfs, Path::new("/Strings/zh_Hans.po")).unwrap();
+        assert_eq!(chinese.locale.as_deref(), Some("zh-hans"));
+    }
+}
@@ -1,20 +1,29 @@
-use std::{borrow::Cow, collections::HashMap, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{bail, Context};
-use memofs::Vfs;
-use rbx_dom_weak::types::{Attributes, Ref};
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::types::{Attributes, Ref, Variant};
 use rbx_reflection::ClassTag;
 
 use crate::{
-    project::{PathNode, Project, ProjectNode},
+    asset_manifest::AssetManifest,
+    glob::Glob,
+    project::{OptionalPathNode, PathNode, Project, ProjectNode},
     snapshot::{
-        InstanceContext, InstanceMetadata, InstanceSnapshot, InstigatingSource, PathIgnoreRule,
-        SyncRule,
+        IgnoreUnknownInstances, InstanceContext, InstanceMetadata, InstanceSnapshot,
+        InstigatingSource, PathIgnoreRule, SyncRule,
     },
     RojoRef,
 };
 
-use super::{emit_legacy_scripts_default, snapshot_from_vfs};
+use super::{
+    apply_class_defaults, emit_legacy_scripts_default, rbxl::snapshot_rbxl, rbxlx::snapshot_rbxlx,
+    resolve_assets, snapshot_from_vfs, validate_snapshot, Middleware,
+};
 
 pub fn snapshot_project(
     context: &InstanceContext,
@@ -31,19 +40,51 @@ pub fn snapshot_project(
 
     let mut context = context.clone();
     context.clear_sync_rules();
+    context.clear_init_file_rules();
 
     let rules = project.glob_ignore_paths.iter().map(|glob| PathIgnoreRule {
         glob: glob.clone(),
         base_path: project.folder_location().to_path_buf(),
     });
 
+    for rule in &project.sync_rules {
+        if rule.middleware == Middleware::Command && rule.command.is_empty() {
+            bail!(
+                "Sync rule '{}' in {} uses the command middleware, but doesn't specify a command",
+                rule.include.as_str(),
+                path.display()
+            );
+        }
+
+        if let Some(class_name) = &rule.class_name {
+            if !crate::reflection::database()
+                .classes
+                .contains_key(class_name.as_str())
+            {
+                bail!(
+                    "Sync rule '{}' in {} uses unknown class {}",
+                    rule.include.as_str(),
+                    path.display(),
+                    class_name
+                );
+            }
+        }
+    }
+
     let sync_rules = project.sync_rules.iter().map(|rule| SyncRule {
         base_path: project.folder_location().to_path_buf(),
         ..rule.clone()
     });
 
     context.add_sync_rules(sync_rules);
+    context.add_init_file_rules(project.init_files.iter().cloned());
     context.add_path_ignore_rules(rules);
+    let mut project_wide_paths = Vec::new();
+    context.set_class_defaults(resolve_class_defaults(
+        &project,
+        vfs,
+        &mut project_wide_paths,
+    )?);
     context.set_emit_legacy_scripts(
         project
             .emit_legacy_scripts
@@ -51,7 +92,28 @@ pub fn snapshot_project(
             .unwrap(),
     );
 
-    match snapshot_project_node(&context, path, project_name, &project.tree, vfs, None)? {
+    // Nested projects inherit strict mode unless they say otherwise.
+    if let Some(strict) = project.strict {
+        context.set_strict(strict);
+    }
+
+    if let Some(escaped_file_names) = project.escaped_file_names {
+        context.set_escaped_file_names(escaped_file_names);
+    }
+
+    if let Some(data_modules) = project.data_modules {
+        context.merge_data_modules(data_modules);
+    }
+
+    if let Some(asset_manifest) = &project.asset_manifest {
+        let manifest_path = project.folder_location().join(asset_manifest);
+        context.set_asset_manifest(AssetManifest::load(vfs, &manifest_path)?);
+        project_wide_paths.push(manifest_path);
+    }
+
+    let tree = project.expand_templates()?;
+
+    match snapshot_project_node(&context, path, project_name, &tree, vfs, None)? {
         Some(found_snapshot) => {
             let mut snapshot = found_snapshot;
             // Setting the instigating source to the project file path is a little
@@ -72,12 +134,70 @@ pub fn snapshot_project(
             // file being updated.
             snapshot.metadata.relevant_paths.push(path.to_path_buf());
 
+            // Files used by classDefaults and the asset manifest can affect
+            // any instance in the project, so changing them re-snapshots the
+            // whole project.
+            snapshot.metadata.relevant_paths.extend(project_wide_paths);
+
             Ok(Some(snapshot))
         }
         None => Ok(None),
     }
 }
 
+/// Resolves the `classDefaults` field of a project into concrete property
+/// values for each class. Paths of any files read for those values are pushed
+/// into `file_paths`.
+fn resolve_class_defaults(
+    project: &Project,
+    vfs: &Vfs,
+    file_paths: &mut Vec<PathBuf>,
+) -> anyhow::Result<BTreeMap<String, BTreeMap<String, Variant>>> {
+    let database = crate::reflection::database();
+    let mut class_defaults = BTreeMap::new();
+
+    for (class_name, unresolved_properties) in &project.class_defaults {
+        if !database.classes.contains_key(class_name.as_str()) {
+            bail!(
+                "Unknown class {} in classDefaults of project at path {}",
+                class_name,
+                project.file_location.display()
+            );
+        }
+
+        let mut properties = BTreeMap::new();
+
+        for (key, unresolved) in unresolved_properties {
+            if matches!(key.as_str(), "Name" | "Parent") {
+                bail!(
+                    "Property '{}' cannot be given a default value. Attempted to set it for class {} in project at path {}",
+                    key,
+                    class_name,
+                    project.file_location.display()
+                );
+            }
+
+            let mut unresolved = unresolved.clone();
+            if let Some(file_path) = unresolved.load_file(vfs, project.folder_location())? {
+                file_paths.push(file_path);
+            }
+
+            let value = unresolved.resolve(class_name, key).with_context(|| {
+                format!(
+                    "Unresolvable property in classDefaults of project at path {}",
+                    project.file_location.display()
+                )
+            })?;
+
+            properties.insert(key.clone(), value);
+        }
+
+        class_defaults.insert(class_name.clone(), properties);
+    }
+
+    Ok(class_defaults)
+}
+
 pub fn snapshot_project_node(
     context: &InstanceContext,
     project_path: &Path,
@@ -85,6 +205,34 @@ pub fn snapshot_project_node(
     node: &ProjectNode,
     vfs: &Vfs,
     parent_class: Option<&str>,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let result = snapshot_project_node_inner(
+        context,
+        project_path,
+        instance_name,
+        node,
+        vfs,
+        parent_class,
+    );
+
+    match &node.expanded_from {
+        Some(template) => result.with_context(|| {
+            format!(
+                "Could not create instance \"{}\" from template \"{}\"",
+                instance_name, template
+            )
+        }),
+        None => result,
+    }
+}
+
+fn snapshot_project_node_inner(
+    context: &InstanceContext,
+    project_path: &Path,
+    instance_name: &str,
+    node: &ProjectNode,
+    vfs: &Vfs,
+    parent_class: Option<&str>,
 ) -> anyhow::Result<Option<InstanceSnapshot>> {
     let project_folder = project_path.parent().unwrap();
 
@@ -95,41 +243,100 @@ pub fn snapshot_project_node(
     let mut class_name_from_path = None;
 
     let name = Cow::Owned(instance_name.to_owned());
+    let mut snapshot_id = Ref::none();
     let mut properties = HashMap::new();
     let mut children = Vec::new();
     let mut metadata = InstanceMetadata::new().context(context);
 
+    if node.select.is_some() && node.path.is_none() {
+        bail!(
+            "$select can only be used together with $path (on instance \"{}\" in project {})",
+            instance_name,
+            project_path.display()
+        );
+    }
+
     if let Some(path_node) = &node.path {
-        let path = path_node.path();
+        if is_multiple_path(vfs, project_folder, path_node)? {
+            if node.select.is_some() {
+                bail!(
+                    "$select cannot be used with a $path that lists multiple paths or globs \
+                     (on instance \"{}\" in project {})",
+                    instance_name,
+                    project_path.display()
+                );
+            }
 
-        // If the path specified in the project is relative, we assume it's
-        // relative to the folder that the project is in, project_folder.
-        let full_path = if path.is_relative() {
-            Cow::Owned(project_folder.join(path))
-        } else {
-            Cow::Borrowed(path)
-        };
+            // Each path (or glob match) becomes a child of this node, which is
+            // a Folder unless the project says otherwise.
+            class_name_from_path = Some(Cow::Borrowed("Folder"));
 
-        if let Some(snapshot) = snapshot_from_vfs(context, vfs, &full_path)? {
-            class_name_from_path = Some(snapshot.class_name);
+            let (path_children, watched_paths) =
+                snapshot_multiple_paths(context, vfs, project_path, path_node.paths())?;
 
-            // Properties from the snapshot are pulled in unchanged, and
-            // overridden by properties set on the project node.
-            properties.reserve(snapshot.properties.len());
-            for (key, value) in snapshot.properties.into_iter() {
-                properties.insert(key, value);
-            }
+            children.extend(path_children);
 
-            // The snapshot's children will be merged with the children defined
-            // in the project node, if there are any.
-            children.reserve(snapshot.children.len());
-            for child in snapshot.children.into_iter() {
-                children.push(child);
-            }
+            // Creating or removing files inside of these folders can change
+            // which paths match, so they need to cause this node to be
+            // snapshotted again.
+            metadata.relevant_paths = watched_paths;
+        } else {
+            let path = &path_node.paths()[0];
 
-            // Take the snapshot's metadata as-is, which will be mutated later
-            // on.
-            metadata = snapshot.metadata;
+            // If the path specified in the project is relative, we assume it's
+            // relative to the folder that the project is in, project_folder.
+            let full_path = if path.is_relative() {
+                Cow::Owned(project_folder.join(path))
+            } else {
+                Cow::Borrowed(path.as_path())
+            };
+
+            let snapshot = match place_file_kind(&full_path) {
+                Some(_) if node.select.is_none() => bail!(
+                    "$path points to the place file {}, so $select must be used to pick \
+                     an instance out of it (on instance \"{}\" in project {})",
+                    full_path.display(),
+                    instance_name,
+                    project_path.display()
+                ),
+                Some(kind) => snapshot_place_file(context, vfs, &full_path, kind)?,
+                None => snapshot_from_vfs(context, vfs, &full_path)?,
+            };
+
+            if let Some(mut snapshot) = snapshot {
+                if let Some(select) = &node.select {
+                    snapshot = select_descendant(snapshot, select).with_context(|| {
+                        format!(
+                            "Could not select \"{}\" from {}",
+                            select,
+                            full_path.display()
+                        )
+                    })?;
+                }
+
+                // Keeping the snapshot's ID lets Refs inside of it keep
+                // pointing at this instance.
+                snapshot_id = snapshot.snapshot_id;
+                class_name_from_path = Some(snapshot.class_name);
+
+                // Properties from the snapshot are pulled in unchanged, and
+                // overridden by properties set on the project node.
+                properties.reserve(snapshot.properties.len());
+                for (key, value) in snapshot.properties.into_iter() {
+                    properties.insert(key, value);
+                }
+
+                // The snapshot's children will be merged with the children defined
+                // in the project node, if there are any.
+                children.reserve(snapshot.children.len());
+                for child in snapshot.children.into_iter() {
+                    children.push(child);
+                }
+
+                // Take the snapshot's metadata as-is, which will be mutated later
+                // on.
+                metadata = snapshot.metadata;
+            }
         }
     }
 
@@ -175,7 +382,7 @@ pub fn snapshot_project_node(
                     project,
                     path,
                     project_path.display(),
-                    node.path.as_ref().unwrap().path().display()
+                    node.path.as_ref().unwrap().paths()[0].display()
                 );
             }
         }
@@ -184,7 +391,7 @@ pub fn snapshot_project_node(
             return Ok(None);
         }
 
-        (_, None, _, Some(PathNode::Required(path))) => {
+        (_, None, _, Some(path_node)) => {
             anyhow::bail!(
                 "Rojo project referred to a file using $path that could not be turned into a Roblox Instance by Rojo.\n\
                 Check that the file exists and is a file type known by Rojo.\n\
@@ -192,7 +399,7 @@ pub fn snapshot_project_node(
                 Project path: {}\n\
                 File $path: {}",
                 project_path.display(),
-                path.display(),
+                path_node.paths()[0].display(),
             );
         }
 
@@ -224,16 +431,31 @@ pub fn snapshot_project_node(
         }
     }
 
+    let project_folder = project_path.parent().unwrap();
+
     for (key, unresolved) in &node.properties {
-        let value = unresolved
-            .clone()
-            .resolve(&class_name, key)
-            .with_context(|| {
-                format!(
-                    "Unresolvable property in project at path {}",
-                    project_path.display()
-                )
-            })?;
+        let mut unresolved = unresolved.clone();
+        if let Some(file_path) = unresolved.load_file(vfs, project_folder)? {
+            metadata.relevant_paths.push(file_path);
+        }
+
+        let ref_path = unresolved.ref_path(&class_name, key).with_context(|| {
+            format!(
+                "Unresolvable property in project at path {}",
+                project_path.display()
+            )
+        })?;
+        if let Some(ref_path) = ref_path {
+            metadata.ref_paths.insert(key.clone(), ref_path);
+            continue;
+        }
+
+        let value = unresolved.resolve(&class_name, key).with_context(|| {
+            format!(
+                "Unresolvable property in project at path {}",
+                project_path.display()
+            )
+        })?;
 
         match key.as_str() {
             "Name" | "Parent" => {
@@ -256,7 +478,12 @@ pub fn snapshot_project_node(
         let mut attributes = Attributes::new();
 
         for (key, unresolved) in &node.attributes {
-            let value = unresolved.clone().resolve_unambiguous().with_context(|| {
+            let mut unresolved = unresolved.clone();
+            if let Some(file_path) = unresolved.load_file(vfs, project_folder)? {
+                metadata.relevant_paths.push(file_path);
+            }
+
+            let value = unresolved.resolve_unambiguous().with_context(|| {
                 format!(
                     "Unresolvable attribute in project at path {}",
                     project_path.display()
@@ -275,12 +502,12 @@ pub fn snapshot_project_node(
     // If the user didn't specify it AND $path was not specified (meaning
     // there's no existing value we'd be stepping on from a project file or meta
     // file), set it to true.
-    if let Some(ignore) = node.ignore_unknown_instances {
-        metadata.ignore_unknown_instances = ignore;
+    if let Some(ignore) = &node.ignore_unknown_instances {
+        metadata.ignore_unknown_instances = ignore.clone();
     } else if node.path.is_none() {
         // TODO: Introduce a strict mode where $ignoreUnknownInstances is never
         // set implicitly.
-        metadata.ignore_unknown_instances = true;
+        metadata.ignore_unknown_instances = IgnoreUnknownInstances::Bool(true);
     }
 
     if let Some(id) = &node.id {
@@ -294,14 +521,304 @@ pub fn snapshot_project_node(
         parent_class.map(|name| name.to_owned()),
     ));
 
-    Ok(Some(InstanceSnapshot {
-        snapshot_id: Ref::none(),
+    let mut snapshot = InstanceSnapshot {
+        snapshot_id,
         name,
         class_name,
         properties,
         children,
         metadata,
-    }))
+    };
+
+    // Children have their defaults and assets applied when they're
+    // snapshotted, so only this node needs them.
+    apply_class_defaults(context, &mut snapshot, false);
+    resolve_assets(context, &mut snapshot, false).with_context(|| {
+        format!(
+            "Could not resolve assets of {} in project {}",
+            instance_name,
+            project_path.display()
+        )
+    })?;
+
+    if context.strict {
+        validate_snapshot(&snapshot, false).with_context(|| {
+            format!(
+                "Invalid instance {} in project {}",
+                instance_name,
+                project_path.display()
+            )
+        })?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PlaceFileKind {
+    Binary,
+    Xml,
+}
+
+/// Tells whether the file at `path` is a place file. Place files aren't
+/// picked up from directories, since a place inside of a place doesn't make
+/// sense, so they can only be used from `$path` along with `$select`.
+fn place_file_kind(path: &Path) -> Option<PlaceFileKind> {
+    match path.extension()?.to_str()? {
+        "rbxl" => Some(PlaceFileKind::Binary),
+        "rbxlx" => Some(PlaceFileKind::Xml),
+        _ => None,
+    }
+}
+
+fn snapshot_place_file(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    kind: PlaceFileKind,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .with_context(|| format!("file name of {} is invalid", path.display()))?;
+
+    match kind {
+        PlaceFileKind::Binary => snapshot_rbxl(context, vfs, path, name),
+        PlaceFileKind::Xml => snapshot_rbxlx(context, vfs, path, name),
+    }
+}
+
+/// Replaces `snapshot` with its descendant at `select`, a list of names
+/// separated by slashes. The descendant takes over the metadata of `snapshot`
+/// so that changes to the file it came from are still picked up.
+///
+/// Refs that point at instances outside of the descendant are cleared, since
+/// those instances won't exist.
+fn select_descendant(
+    mut snapshot: InstanceSnapshot,
+    select: &str,
+) -> anyhow::Result<InstanceSnapshot> {
+    let metadata = std::mem::take(&mut snapshot.metadata);
+
+    let mut selected = snapshot;
+    for name in select.split('/') {
+        selected = selected
+            .children
+            .into_iter()
+            .find(|child| child.name == name)
+            .with_context(|| format!("There is no instance named \"{}\"", name))?;
+    }
+
+    let mut ids = HashSet::new();
+    collect_snapshot_ids(&selected, &mut ids);
+    clear_outside_refs(&mut selected, &ids);
+
+    selected.metadata = metadata;
+    Ok(selected)
+}
+
+fn collect_snapshot_ids(snapshot: &InstanceSnapshot, ids: &mut HashSet<Ref>) {
+    if snapshot.snapshot_id.is_some() {
+        ids.insert(snapshot.snapshot_id);
+    }
+
+    for child in &snapshot.children {
+        collect_snapshot_ids(child, ids);
+    }
+}
+
+fn clear_outside_refs(snapshot: &mut InstanceSnapshot, ids: &HashSet<Ref>) {
+    for value in snapshot.properties.values_mut() {
+        if let Variant::Ref(referent) = value {
+            if referent.is_some() && !ids.contains(referent) {
+                *value = Variant::Ref(Ref::none());
+            }
+        }
+    }
+
+    for child in &mut snapshot.children {
+        clear_outside_refs(child, ids);
+    }
+}
+
+/// Tells whether the given `$path` should be treated as a list of sources that
+/// are each turned into a child, rather than as a single source.
+///
+/// This is the case for lists of paths, and for paths that look like globs.
+/// Paths that look like globs but exist on disk are treated literally so that
+/// oddly-named files keep working.
+fn is_multiple_path(
+    vfs: &Vfs,
+    project_folder: &Path,
+    path_node: &PathNode,
+) -> anyhow::Result<bool> {
+    let path = match path_node {
+        PathNode::Multiple(_) => return Ok(true),
+        PathNode::Required(path) => path,
+        PathNode::Optional(OptionalPathNode { optional }) => optional,
+    };
+
+    if !is_glob(path) {
+        return Ok(false);
+    }
+
+    let exists = vfs
+        .metadata(project_folder.join(path))
+        .with_not_found()?
+        .is_some();
+
+    Ok(!exists)
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_str()
+        .map(|path| path.contains(['*', '?', '[', '{']))
+        .unwrap_or(false)
+}
+
+/// Snapshots every path in the given list, expanding any globs. Returns the
+/// resulting snapshots along with the folders that were searched for matches.
+fn snapshot_multiple_paths(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    project_path: &Path,
+    paths: &[PathBuf],
+) -> anyhow::Result<(Vec<InstanceSnapshot>, Vec<PathBuf>)> {
+    let project_folder = project_path.parent().unwrap();
+
+    let mut children = Vec::new();
+    let mut watched_paths = Vec::new();
+
+    for path in paths {
+        let full_path = project_folder.join(path);
+
+        if !is_glob(path) {
+            match snapshot_from_vfs(context, vfs, &full_path)? {
+                Some(snapshot) => children.push(snapshot),
+                None => bail!(
+                    "Rojo project referred to a file using $path that could not be turned into a Roblox Instance by Rojo.\n\
+                     Check that the file exists and is a file type known by Rojo.\n\
+                     \n\
+                     Project path: {}\n\
+                     File $path: {}",
+                    project_path.display(),
+                    path.display(),
+                ),
+            }
+
+            continue;
+        }
+
+        let glob_match = GlobMatch::new(&full_path)
+            .with_context(|| format!("Invalid glob in $path: {}", path.display()))?;
+
+        let mut matches = Vec::new();
+        glob_match.find(context, vfs, &glob_match.base, 1, &mut matches)?;
+        matches.sort();
+
+        for matched_path in matches {
+            if let Some(mut snapshot) = snapshot_from_vfs(context, vfs, &matched_path)? {
+                if let Some(name) = glob_match.name_for_match(&matched_path) {
+                    snapshot.name = Cow::Owned(name.to_owned());
+                    snapshot.metadata.name_override = Some(name.to_owned());
+                }
+
+                children.push(snapshot);
+            }
+        }
+
+        watched_paths.push(glob_match.base);
+    }
+
+    Ok((children, watched_paths))
+}
+
+/// A glob from a `$path`, split into the literal folder it starts from and the
+/// pattern that the rest of a path is matched against.
+struct GlobMatch {
+    base: PathBuf,
+    pattern: Glob,
+    depth: Option<usize>,
+    name_from_folder: bool,
+}
+
+impl GlobMatch {
+    fn new(full_path: &Path) -> anyhow::Result<Self> {
+        let components: Vec<_> = full_path.components().collect();
+        let first_glob = components
+            .iter()
+            .position(|component| is_glob(component.as_os_str().as_ref()))
+            .unwrap_or(components.len());
+
+        let base: PathBuf = components[..first_glob].iter().collect();
+        let rest: Vec<_> = components[first_glob..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+
+        let recursive = rest.iter().any(|component| component.contains("**"));
+
+        Ok(Self {
+            base,
+            pattern: Glob::new(&rest.join("/"))?,
+            depth: if recursive { None } else { Some(rest.len()) },
+            // If the glob selects folders by name and then descends into them,
+            // like `packages/*/src`, every match would otherwise end up with
+            // the same name.
+            name_from_folder: !recursive && rest.len() > 1,
+        })
+    }
+
+    fn find(
+        &self,
+        context: &InstanceContext,
+        vfs: &Vfs,
+        folder: &Path,
+        depth: usize,
+        matches: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let entries = match vfs.read_dir(folder).with_not_found()? {
+            Some(entries) => entries,
+            None => return Ok(()),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+
+            if !context
+                .path_ignore_rules
+                .iter()
+                .all(|rule| rule.passes(path))
+            {
+                continue;
+            }
+
+            let relative = path.strip_prefix(&self.base)?;
+
+            if self.pattern.is_match(relative) {
+                matches.push(path.to_path_buf());
+            } else if self.depth.map_or(true, |max| depth < max) && vfs.metadata(path)?.is_dir() {
+                self.find(context, vfs, path, depth + 1, matches)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name_for_match<'a>(&self, matched_path: &'a Path) -> Option<&'a str> {
+        if !self.name_from_folder {
+            return None;
+        }
+
+        matched_path
+            .strip_prefix(&self.base)
+            .ok()?
+            .components()
+            .next()?
+            .as_os_str()
+            .to_str()
+    }
 }
 
 fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'static, str>> {
@@ -314,7 +831,7 @@ fn infer_class_name(name: &str, parent_class: Option<&str>) -> Option<Cow<'stati
         // Members of DataModel with names that match known services are
         // probably supposed to be those services.
 
-        let descriptor = rbx_reflection_database::get().classes.get(name)?;
+        let descriptor = crate::reflection::database().classes.get(name)?;
 
         if descriptor.tags.contains(&ClassTag::Service) {
             return Some(Cow::Owned(name.to_owned()));
@@ -775,4 +1292,245 @@ mod test {
 
         insta::assert_yaml_snapshot!(instance_snapshot);
     }
+
+    #[test]
+    fn strict_mode() {
+        let _ = env_logger::try_init();
+
+        let snapshot_with = |strict: bool, tree: &str| {
+            let project = format!(
+                r#"{{ "name": "strict", "strict": {}, "tree": {} }}"#,
+                strict, tree
+            );
+
+            let mut imfs = InMemoryFs::new();
+            imfs.load_snapshot(
+                "/foo",
+                VfsSnapshot::dir([
+                    ("default.project.json", VfsSnapshot::file(project)),
+                    (
+                        "bogus.model.json",
+                        VfsSnapshot::file(
+                            r#"{
+                                "className": "Folder",
+                                "children": [{
+                                    "name": "Child",
+                                    "className": "Folder",
+                                    "properties": { "Bogus": { "String": "hi" } }
+                                }]
+                            }"#,
+                        ),
+                    ),
+                ]),
+            )
+            .unwrap();
+
+            let vfs = Vfs::new(imfs);
+
+            snapshot_project(
+                &InstanceContext::default(),
+                &vfs,
+                Path::new("/foo/default.project.json"),
+                "strict",
+            )
+            .map_err(|err| format!("{:#}", err))
+        };
+
+        let valid = r#"{
+            "$className": "StringValue",
+            "$properties": { "Value": "hello" }
+        }"#;
+        let wrong_type = r#"{
+            "$className": "StringValue",
+            "$properties": { "Value": { "Bool": true } }
+        }"#;
+        let unknown_class = r#"{ "$className": "NotAClass" }"#;
+        let unknown_property = r#"{
+            "$className": "Folder",
+            "Model": { "$path": "bogus.model.json" }
+        }"#;
+
+        assert!(snapshot_with(true, valid).is_ok());
+
+        let error = snapshot_with(true, wrong_type).unwrap_err();
+        assert!(
+            error.contains("StringValue.Value of type Bool, but it should be String"),
+            "{}",
+            error
+        );
+
+        let error = snapshot_with(true, unknown_class).unwrap_err();
+        assert!(error.contains("unknown class NotAClass"), "{}", error);
+
+        let error = snapshot_with(true, unknown_property).unwrap_err();
+        assert!(error.contains("unknown property Folder.Bogus"), "{}", error);
+        assert!(error.contains("bogus.model.json"), "{}", error);
+
+        // Without strict mode, all of these are passed through as-is.
+        for tree in [wrong_type, unknown_class, unknown_property] {
+            assert!(snapshot_with(false, tree).is_ok());
+        }
+    }
+
+    #[test]
+    fn sync_rule_validation() {
+        let _ = env_logger::try_init();
+
+        let snapshot_with = |rule: &str| {
+            let project = format!(
+                r#"{{ "name": "rules", "tree": {{ "$path": "src" }}, "syncRules": [{}] }}"#,
+                rule
+            );
+
+            let mut imfs = InMemoryFs::new();
+            imfs.load_snapshot(
+                "/foo",
+                VfsSnapshot::dir([
+                    ("default.project.json", VfsSnapshot::file(project)),
+                    (
+                        "src",
+                        VfsSnapshot::dir([("hello.data", VfsSnapshot::file("Hello!"))]),
+                    ),
+                ]),
+            )
+            .unwrap();
+
+            let vfs = Vfs::new(imfs);
+
+            snapshot_project(
+                &InstanceContext::default(),
+                &vfs,
+                Path::new("/foo/default.project.json"),
+                "rules",
+            )
+            .map_err(|err| format!("{:#}", err))
+        };
+
+        let snapshot = snapshot_with(
+            r#"{ "pattern": "*.data", "use": "text", "className": "Configuration" }"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(snapshot.children[0].class_name, "Configuration");
+
+        // Configuration doesn't have the Value set by the text middleware.
+        assert!(snapshot.children[0].properties.is_empty());
+
+        let error = snapshot_with(r#"{ "pattern": "*.data", "use": "text", "className": "Nope" }"#)
+            .unwrap_err();
+        assert!(error.contains("uses unknown class Nope"), "{}", error);
+
+        let error = snapshot_with(
+            r#"{ "pattern": "*.data", "use": "text", "properties": { "Bogus": true } }"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Could not apply sync rule '*.data'"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("Unknown property StringValue.Bogus"),
+            "{}",
+            error
+        );
+
+        // Place files can only be used from $path with $select.
+        for middleware in ["rbxl", "rbxlx"] {
+            let rule = format!(r#"{{ "pattern": "*.data", "use": "{}" }}"#, middleware);
+            let error = snapshot_with(&rule).unwrap_err();
+            assert!(error.contains("unknown variant"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn select_validation() {
+        let _ = env_logger::try_init();
+
+        let snapshot_with = |tree: &str| {
+            let project = format!(r#"{{ "name": "select", "tree": {} }}"#, tree);
+            let place = r#"<roblox version="4">
+                <Item class="Workspace" referent="0">
+                    <Properties>
+                        <string name="Name">Workspace</string>
+                    </Properties>
+                </Item>
+            </roblox>"#;
+
+            let mut imfs = InMemoryFs::new();
+            imfs.load_snapshot(
+                "/foo",
+                VfsSnapshot::dir([
+                    ("default.project.json", VfsSnapshot::file(project)),
+                    (
+                        "map.rbxmx",
+                        VfsSnapshot::file(
+                            r#"<roblox version="4">
+                                <Item class="Folder" referent="0">
+                                    <Item class="Part" referent="1">
+                                        <Properties>
+                                            <string name="Name">Spawn</string>
+                                        </Properties>
+                                    </Item>
+                                </Item>
+                            </roblox>"#,
+                        ),
+                    ),
+                    ("place.rbxlx", VfsSnapshot::file(place)),
+                    (
+                        "src",
+                        VfsSnapshot::dir([("stray.rbxlx", VfsSnapshot::file(place))]),
+                    ),
+                ]),
+            )
+            .unwrap();
+
+            let vfs = Vfs::new(imfs);
+
+            snapshot_project(
+                &InstanceContext::default(),
+                &vfs,
+                Path::new("/foo/default.project.json"),
+                "select",
+            )
+            .map_err(|err| format!("{:#}", err))
+        };
+
+        let snapshot = snapshot_with(r#"{ "$path": "map.rbxmx", "$select": "Spawn" }"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.class_name, "Part");
+        assert_eq!(snapshot.name, "select");
+
+        let error = snapshot_with(r#"{ "$path": "map.rbxmx", "$select": "Missing" }"#).unwrap_err();
+        assert!(
+            error.contains("There is no instance named \"Missing\""),
+            "{}",
+            error
+        );
+
+        let error = snapshot_with(r#"{ "$className": "Folder", "$select": "Spawn" }"#).unwrap_err();
+        assert!(
+            error.contains("$select can only be used together with $path"),
+            "{}",
+            error
+        );
+
+        let error = snapshot_with(r#"{ "$path": "*.rbxmx", "$select": "Spawn" }"#).unwrap_err();
+        assert!(error.contains("multiple paths"), "{}", error);
+
+        let snapshot = snapshot_with(r#"{ "$path": "place.rbxlx", "$select": "Workspace" }"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.class_name, "Workspace");
+
+        let error = snapshot_with(r#"{ "$path": "place.rbxlx" }"#).unwrap_err();
+        assert!(error.contains("$select must be used"), "{}", error);
+
+        // Place files in synced folders are ignored rather than becoming a
+        // DataModel inside of the tree.
+        let snapshot = snapshot_with(r#"{ "$path": "src" }"#).unwrap().unwrap();
+        assert_eq!(snapshot.class_name, "Folder");
+        assert!(snapshot.children.is_empty());
+    }
 }
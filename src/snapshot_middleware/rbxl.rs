@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use anyhow::Context;
+use memofs::Vfs;
+use rbx_dom_weak::{types::Ref, WeakDom};
+
+use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+
+#[profiling::function]
+pub fn snapshot_rbxl(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let temp_tree = rbx_binary::from_reader(vfs.read(path)?.as_slice())
+        .with_context(|| format!("Malformed rbxl file: {}", path.display()))?;
+
+    Ok(Some(snapshot_place_tree(context, path, name, temp_tree)))
+}
+
+/// Turns a tree decoded from a place file into a DataModel snapshot whose
+/// children are the place's services. Projects usually pick a single service
+/// or subtree out of it with `$select`.
+pub fn snapshot_place_tree(
+    context: &InstanceContext,
+    path: &Path,
+    name: &str,
+    temp_tree: WeakDom,
+) -> InstanceSnapshot {
+    let root_ref = temp_tree.root_ref();
+
+    let mut snapshot = InstanceSnapshot::from_tree(temp_tree, root_ref)
+        .name(name)
+        .class_name("DataModel")
+        .metadata(
+            InstanceMetadata::new()
+                .instigating_source(path)
+                .relevant_paths(vec![path.to_path_buf()])
+                .context(context),
+        );
+    snapshot.snapshot_id = Ref::none();
+    snapshot.properties.clear();
+
+    snapshot
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn place_from_vfs() {
+        let mut place = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = place.root_ref();
+        let workspace = place.insert(root_ref, InstanceBuilder::new("Workspace"));
+        place.insert(
+            workspace,
+            InstanceBuilder::new("Part").with_name("Baseplate"),
+        );
+        let lighting = place.insert(root_ref, InstanceBuilder::new("Lighting"));
+
+        let mut contents = Vec::new();
+        rbx_binary::to_writer(&mut contents, &place, &[workspace, lighting]).unwrap();
+
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.rbxl", VfsSnapshot::file(contents))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_rbxl(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxl"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.name, "foo");
+        assert_eq!(instance_snapshot.class_name, "DataModel");
+        assert_eq!(instance_snapshot.children.len(), 2);
+        assert_eq!(instance_snapshot.children[0].class_name, "Workspace");
+        assert_eq!(instance_snapshot.children[0].children[0].name, "Baseplate");
+        assert_eq!(instance_snapshot.children[1].class_name, "Lighting");
+    }
+}
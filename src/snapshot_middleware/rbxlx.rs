@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use anyhow::Context;
+use memofs::Vfs;
+
+use crate::snapshot::{InstanceContext, InstanceSnapshot};
+
+use super::rbxl::snapshot_place_tree;
+
+pub fn snapshot_rbxlx(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let options = rbx_xml::DecodeOptions::new()
+        .property_behavior(rbx_xml::DecodePropertyBehavior::ReadUnknown);
+
+    let temp_tree = rbx_xml::from_reader(vfs.read(path)?.as_slice(), options)
+        .with_context(|| format!("Malformed rbxlx file: {}", path.display()))?;
+
+    Ok(Some(snapshot_place_tree(context, path, name, temp_tree)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    #[test]
+    fn place_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.rbxlx",
+            VfsSnapshot::file(
+                r#"
+                    <roblox version="4">
+                        <Item class="Workspace" referent="0">
+                            <Properties>
+                                <string name="Name">Workspace</string>
+                            </Properties>
+                            <Item class="Part" referent="1">
+                                <Properties>
+                                    <string name="Name">Baseplate</string>
+                                </Properties>
+                            </Item>
+                        </Item>
+                        <Item class="Lighting" referent="2">
+                            <Properties>
+                                <string name="Name">Lighting</string>
+                            </Properties>
+                        </Item>
+                    </roblox>
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_rbxlx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxlx"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.name, "foo");
+        assert_eq!(instance_snapshot.class_name, "DataModel");
+        assert_eq!(instance_snapshot.properties, Default::default());
+        assert_eq!(instance_snapshot.children.len(), 2);
+        assert_eq!(instance_snapshot.children[0].name, "Workspace");
+        assert_eq!(instance_snapshot.children[0].children[0].name, "Baseplate");
+    }
+}
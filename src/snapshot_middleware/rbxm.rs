@@ -1,10 +1,13 @@
-use std::path::Path;
+use std::{borrow::Cow, path::Path};
 
 use anyhow::Context;
-use memofs::Vfs;
+use memofs::{IoResultExt, Vfs};
+use rbx_dom_weak::{types::Ref, WeakDom};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
+use super::{apply_sync_rule, meta_file::AdjacentMetadata, util::adjacent_meta_path};
+
 #[profiling::function]
 pub fn snapshot_rbxm(
     context: &InstanceContext,
@@ -15,28 +18,73 @@ pub fn snapshot_rbxm(
     let temp_tree = rbx_binary::from_reader(vfs.read(path)?.as_slice())
         .with_context(|| format!("Malformed rbxm file: {}", path.display()))?;
 
+    snapshot_model_tree(context, vfs, path, name, temp_tree)
+}
+
+/// Turns a tree decoded from a model file into a snapshot. Models with one
+/// top-level instance become that instance. Models with several become a
+/// Folder containing all of them, unless the model's meta file sets a
+/// different `className`.
+pub fn snapshot_model_tree(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+    temp_tree: WeakDom,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let meta_path = adjacent_meta_path(context, path, name);
+    let mut metadata = match vfs.read(&meta_path).with_not_found()? {
+        Some(meta_contents) => Some(AdjacentMetadata::from_slice(
+            &meta_contents,
+            meta_path.clone(),
+        )?),
+        None => None,
+    };
+
     let root_instance = temp_tree.root();
     let children = root_instance.children();
 
-    if children.len() == 1 {
-        let child = children[0];
-        let snapshot = InstanceSnapshot::from_tree(temp_tree, child)
-            .name(name)
-            .metadata(
-                InstanceMetadata::new()
-                    .instigating_source(path)
-                    .relevant_paths(vec![path.to_path_buf()])
-                    .context(context),
-            );
-
-        Ok(Some(snapshot))
-    } else {
+    if children.is_empty() {
         anyhow::bail!(
-            "Rojo currently only supports model files with one top-level instance.\n\n \
+            "Rojo does not support model files with no top-level instances.\n\n \
              Check the model file at path {}",
             path.display()
         );
     }
+
+    let mut snapshot = if children.len() == 1 {
+        let child = children[0];
+        InstanceSnapshot::from_tree(temp_tree, child)
+    } else {
+        let root_ref = temp_tree.root_ref();
+        let class_name = metadata
+            .as_mut()
+            .and_then(|metadata| metadata.class_name.take())
+            .unwrap_or_else(|| "Folder".to_owned());
+
+        // Converting the root keeps every top-level instance in one snapshot,
+        // so Refs between them still point at each other.
+        let mut snapshot = InstanceSnapshot::from_tree(temp_tree, root_ref);
+        snapshot.snapshot_id = Ref::none();
+        snapshot.class_name = Cow::Owned(class_name);
+        snapshot.properties.clear();
+        snapshot
+    };
+
+    snapshot = snapshot.name(name).metadata(
+        InstanceMetadata::new()
+            .instigating_source(path)
+            .relevant_paths(vec![path.to_path_buf(), meta_path])
+            .context(context),
+    );
+
+    apply_sync_rule(context, vfs, path, &mut snapshot)?;
+
+    if let Some(metadata) = &mut metadata {
+        metadata.apply_all(vfs, &mut snapshot)?;
+    }
+
+    Ok(Some(snapshot))
 }
 
 #[cfg(test)]
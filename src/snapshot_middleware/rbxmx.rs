@@ -3,7 +3,9 @@ use std::path::Path;
 use anyhow::Context;
 use memofs::Vfs;
 
-use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
+use crate::snapshot::{InstanceContext, InstanceSnapshot};
+
+use super::rbxm::snapshot_model_tree;
 
 pub fn snapshot_rbxmx(
     context: &InstanceContext,
@@ -17,28 +19,7 @@ pub fn snapshot_rbxmx(
     let temp_tree = rbx_xml::from_reader(vfs.read(path)?.as_slice(), options)
         .with_context(|| format!("Malformed rbxm file: {}", path.display()))?;
 
-    let root_instance = temp_tree.root();
-    let children = root_instance.children();
-
-    if children.len() == 1 {
-        let child = children[0];
-        let snapshot = InstanceSnapshot::from_tree(temp_tree, child)
-            .name(name)
-            .metadata(
-                InstanceMetadata::new()
-                    .instigating_source(path)
-                    .relevant_paths(vec![path.to_path_buf()])
-                    .context(context),
-            );
-
-        Ok(Some(snapshot))
-    } else {
-        anyhow::bail!(
-            "Rojo currently only supports model files with one top-level instance.\n\n \
-             Check the model file at path {}",
-            path.display()
-        );
-    }
+    snapshot_model_tree(context, vfs, path, name, temp_tree)
 }
 
 #[cfg(test)]
@@ -46,6 +27,7 @@ mod test {
     use super::*;
 
     use memofs::{InMemoryFs, VfsSnapshot};
+    use rbx_dom_weak::types::Variant;
 
     #[test]
     fn plain_folder() {
@@ -82,4 +64,143 @@ mod test {
         assert_eq!(instance_snapshot.properties, Default::default());
         assert_eq!(instance_snapshot.children, Vec::new());
     }
+
+    #[test]
+    fn multiple_top_level_instances() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.rbxmx",
+            VfsSnapshot::file(
+                r#"
+                    <roblox version="4">
+                        <Item class="Part" referent="0">
+                            <Properties>
+                                <string name="Name">Target</string>
+                            </Properties>
+                        </Item>
+                        <Item class="ObjectValue" referent="1">
+                            <Properties>
+                                <string name="Name">Pointer</string>
+                                <Ref name="Value">0</Ref>
+                            </Properties>
+                        </Item>
+                    </roblox>
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxmx"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.name, "foo");
+        assert_eq!(instance_snapshot.class_name, "Folder");
+        assert_eq!(instance_snapshot.properties, Default::default());
+        assert_eq!(instance_snapshot.children.len(), 2);
+
+        let target = &instance_snapshot.children[0];
+        let pointer = &instance_snapshot.children[1];
+        assert_eq!(target.name, "Target");
+        assert_eq!(
+            pointer.properties.get("Value"),
+            Some(&Variant::Ref(target.snapshot_id))
+        );
+    }
+
+    #[test]
+    fn multiple_top_level_instances_class_name() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.rbxmx",
+            VfsSnapshot::file(
+                r#"
+                    <roblox version="4">
+                        <Item class="Part" referent="0" />
+                        <Item class="Part" referent="1" />
+                    </roblox>
+                "#,
+            ),
+        )
+        .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(r#"{ "className": "Model" }"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let instance_snapshot = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxmx"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(instance_snapshot.class_name, "Model");
+        assert_eq!(instance_snapshot.children.len(), 2);
+    }
+
+    #[test]
+    fn single_top_level_instance_class_name() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.rbxmx",
+            VfsSnapshot::file(
+                r#"
+                    <roblox version="4">
+                        <Item class="Part" referent="0" />
+                    </roblox>
+                "#,
+            ),
+        )
+        .unwrap();
+        imfs.load_snapshot(
+            "/foo.meta.json",
+            VfsSnapshot::file(r#"{ "className": "Model" }"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let result = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxmx"),
+            "foo",
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn no_top_level_instances() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.rbxmx",
+            VfsSnapshot::file(r#"<roblox version="4"></roblox>"#),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs);
+
+        let result = snapshot_rbxmx(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.rbxmx"),
+            "foo",
+        );
+
+        assert!(result.is_err());
+    }
 }
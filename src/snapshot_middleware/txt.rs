@@ -4,7 +4,7 @@ use memofs::{IoResultExt, Vfs};
 
 use crate::snapshot::{InstanceContext, InstanceMetadata, InstanceSnapshot};
 
-use super::meta_file::AdjacentMetadata;
+use super::{apply_sync_rule, meta_file::AdjacentMetadata, util::adjacent_meta_path};
 
 pub fn snapshot_txt(
     context: &InstanceContext,
@@ -17,7 +17,7 @@ pub fn snapshot_txt(
 
     let properties = [("Value".to_owned(), contents_str.into())];
 
-    let meta_path = path.with_file_name(format!("{}.meta.json", name));
+    let meta_path = adjacent_meta_path(context, path, name);
 
     let mut snapshot = InstanceSnapshot::new()
         .name(name)
@@ -30,9 +30,11 @@ pub fn snapshot_txt(
                 .context(context),
         );
 
+    apply_sync_rule(context, vfs, path, &mut snapshot)?;
+
     if let Some(meta_contents) = vfs.read(&meta_path).with_not_found()? {
         let mut metadata = AdjacentMetadata::from_slice(&meta_contents, meta_path)?;
-        metadata.apply_all(&mut snapshot)?;
+        metadata.apply_all(vfs, &mut snapshot)?;
     }
 
     Ok(Some(snapshot))
@@ -1,7 +1,13 @@
-use std::path::Path;
+use std::{
+    borrow::Cow,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
 
+use crate::snapshot::InstanceContext;
+
 /// If the given string ends up with the given suffix, returns the portion of
 /// the string before the suffix.
 pub fn match_trailing<'a>(input: &'a str, suffix: &str) -> Option<&'a str> {
@@ -42,7 +48,222 @@ where
     }
 }
 
+/// Device names that Windows reserves, which can't be used as the name of a
+/// file even when followed by an extension.
+const RESERVED_FILE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turns an instance name into something that can be used as a file name on
+/// every platform. Characters that can't appear in file names are written as
+/// `%` followed by two hex digits, like `%2F` for `/`. The same is done for
+/// trailing dots and spaces, the first character of reserved names like
+/// `CON`, and any `%` that would otherwise be read as an escape.
+///
+/// This is the inverse of `unescape_file_name`.
+pub fn escape_file_name(name: &str) -> Cow<'_, str> {
+    let is_reserved = RESERVED_FILE_NAMES.iter().any(|reserved| {
+        name.split('.')
+            .next()
+            .unwrap()
+            .eq_ignore_ascii_case(reserved)
+    });
+    let trailing_start = name.trim_end_matches(['.', ' ']).len();
+
+    let mut escaped = String::with_capacity(name.len());
+    let mut changed = false;
+
+    for (index, c) in name.char_indices() {
+        let needs_escape = match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => true,
+            '%' => decode_escape(&name[index..]).is_some(),
+            c if c.is_ascii_control() => true,
+            _ => (index == 0 && is_reserved) || index >= trailing_start,
+        };
+
+        if needs_escape {
+            // Everything that needs escaping is ASCII, so it fits in a byte.
+            write!(escaped, "%{:02X}", c as u32).unwrap();
+            changed = true;
+        } else {
+            escaped.push(c);
+        }
+    }
+
+    if changed {
+        Cow::Owned(escaped)
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Turns a file name back into the instance name it represents by decoding
+/// any escapes written by `escape_file_name`. A `%` that isn't followed by
+/// two hex digits for an ASCII character is left alone.
+pub fn unescape_file_name(file_name: &str) -> Cow<'_, str> {
+    if !file_name.contains('%') {
+        return Cow::Borrowed(file_name);
+    }
+
+    let mut name = String::with_capacity(file_name.len());
+    let mut rest = file_name;
+
+    while let Some(index) = rest.find('%') {
+        name.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        match decode_escape(rest) {
+            Some(c) => {
+                name.push(c);
+                rest = &rest[3..];
+            }
+            None => {
+                name.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    name.push_str(rest);
+    Cow::Owned(name)
+}
+
+/// If the given string starts with an escape like `%2F`, returns the
+/// character that it stands for.
+fn decode_escape(input: &str) -> Option<char> {
+    let digits = input.strip_prefix('%')?.get(..2)?;
+    let value = u8::from_str_radix(digits, 16).ok()?;
+
+    if digits.starts_with(['+', '-']) || !value.is_ascii() {
+        return None;
+    }
+
+    Some(value as char)
+}
+
+/// Returns the name of the instance that a file or folder named `file_name`
+/// turns into. Escapes like `%2F` are only decoded for projects that set
+/// `escapedFileNames`, since existing files can have names that happen to
+/// look like escapes.
+pub fn instance_name_from_file_name<'a>(
+    context: &InstanceContext,
+    file_name: &'a str,
+) -> Cow<'a, str> {
+    if context.escaped_file_names {
+        unescape_file_name(file_name)
+    } else {
+        Cow::Borrowed(file_name)
+    }
+}
+
+/// Returns the path of the meta file that applies to the instance with the
+/// given name, which was created from the file at `path`.
+///
+/// The meta file is named after the start of the file's name that the
+/// instance name came from, so escapes are kept as they're written on disk,
+/// like `a%41.txt` using `a%41.meta.json`. If the name didn't come from the
+/// file name, the instance name is used instead, escaped if the project uses
+/// escaped file names.
+pub fn adjacent_meta_path(context: &InstanceContext, path: &Path, name: &str) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .unwrap_or_default();
+
+    let stem = file_name
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(file_name.len()))
+        .rev()
+        .map(|end| &file_name[..end])
+        .find(|stem| instance_name_from_file_name(context, stem) == name);
+
+    let stem = match stem {
+        Some(stem) => Cow::Borrowed(stem),
+        None if context.escaped_file_names => escape_file_name(name),
+        None => Cow::Borrowed(name),
+    };
+
+    path.with_file_name(format!("{}.meta.json", stem))
+}
+
 // TEMP function until rojo 8.0, when it can be replaced with bool::default (aka false)
 pub fn emit_legacy_scripts_default() -> Option<bool> {
     Some(true)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_file_names() {
+        assert_eq!(escape_file_name("Normal Name"), "Normal Name");
+        assert_eq!(escape_file_name("A/B:C?"), "A%2FB%3AC%3F");
+        assert_eq!(escape_file_name("Wait..."), "Wait%2E%2E%2E");
+        assert_eq!(escape_file_name("Trailing "), "Trailing%20");
+        assert_eq!(escape_file_name("con"), "%63on");
+        assert_eq!(escape_file_name("LPT1.backup"), "%4CPT1.backup");
+        assert_eq!(escape_file_name("Console"), "Console");
+        assert_eq!(escape_file_name("100%"), "100%");
+        assert_eq!(escape_file_name("%2F"), "%252F");
+    }
+
+    #[test]
+    fn unescape_file_names() {
+        assert_eq!(unescape_file_name("Normal Name"), "Normal Name");
+        assert_eq!(unescape_file_name("A%2FB%3aC%3F"), "A/B:C?");
+        assert_eq!(unescape_file_name("100%"), "100%");
+        assert_eq!(unescape_file_name("%ZZ%+1%E9"), "%ZZ%+1%E9");
+
+        for name in [
+            "A/B:C?", "Wait...", "con", "100%", "%2F", "%%41", "tab\t", ".",
+        ] {
+            assert_eq!(unescape_file_name(&escape_file_name(name)), name);
+        }
+    }
+
+    #[test]
+    fn adjacent_meta_paths() {
+        let mut context = InstanceContext::new();
+        context.set_escaped_file_names(true);
+
+        let cases = [
+            ("/src/Aux.txt", "Aux", "/src/Aux.meta.json"),
+            ("/src/%41ux.txt", "Aux", "/src/%41ux.meta.json"),
+            ("/src/a%41.server.luau", "aA", "/src/a%41.meta.json"),
+            ("/src/A%2fB.json", "A/B", "/src/A%2fB.meta.json"),
+            ("/src/Wait.txt.txt", "Wait.txt", "/src/Wait.txt.meta.json"),
+            (
+                "/src/foo.txt",
+                "Renamed/Name",
+                "/src/Renamed%2FName.meta.json",
+            ),
+        ];
+
+        for (path, name, meta_path) in cases {
+            assert_eq!(
+                adjacent_meta_path(&context, Path::new(path), name),
+                PathBuf::from(meta_path),
+                "meta path for {}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn escapes_are_opt_in() {
+        let context = InstanceContext::new();
+
+        assert_eq!(instance_name_from_file_name(&context, "A%2FB"), "A%2FB");
+        assert_eq!(
+            adjacent_meta_path(&context, Path::new("/src/100%25.txt"), "100%25"),
+            PathBuf::from("/src/100%25.meta.json")
+        );
+        assert_eq!(
+            adjacent_meta_path(&context, Path::new("/src/foo.txt"), "100%25"),
+            PathBuf::from("/src/100%25.meta.json")
+        );
+    }
+}
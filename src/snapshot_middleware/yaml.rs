@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use memofs::Vfs;
+
+use crate::{
+    lua_ast::Expression,
+    snapshot::{InstanceContext, InstanceSnapshot},
+};
+
+use super::data_module::snapshot_data_module;
+
+pub fn snapshot_yaml(
+    context: &InstanceContext,
+    vfs: &Vfs,
+    path: &Path,
+    name: &str,
+) -> anyhow::Result<Option<InstanceSnapshot>> {
+    let contents = vfs.read(path)?;
+
+    let value: serde_yaml::Value = serde_yaml::from_slice(&contents)
+        .with_context(|| format!("File contains malformed YAML: {}", path.display()))?;
+
+    let as_lua = yaml_to_lua_value(value)
+        .with_context(|| format!("Could not convert YAML to Lua: {}", path.display()))?;
+
+    Ok(Some(snapshot_data_module(
+        context, vfs, path, name, as_lua,
+    )?))
+}
+
+fn yaml_to_lua_value(value: serde_yaml::Value) -> anyhow::Result<Expression> {
+    use serde_yaml::Value;
+
+    Ok(match value {
+        Value::Null => Expression::Nil,
+        Value::Bool(value) => Expression::Bool(value),
+        Value::Number(value) => Expression::Number(value.as_f64().unwrap()),
+        Value::String(value) => Expression::String(value),
+        Value::Sequence(values) => Expression::Array(
+            values
+                .into_iter()
+                .map(yaml_to_lua_value)
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Mapping(values) => Expression::table(
+            values
+                .into_iter()
+                .map(|(key, value)| Ok((yaml_to_lua_key(key)?, yaml_to_lua_value(value)?)))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+    })
+}
+
+/// YAML allows any value to be used as a mapping key, but only scalars have a
+/// sensible representation as a Lua table key.
+fn yaml_to_lua_key(key: serde_yaml::Value) -> anyhow::Result<Expression> {
+    use serde_yaml::Value;
+
+    match key {
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => yaml_to_lua_value(key),
+        Value::Null => bail!("null cannot be used as a table key"),
+        Value::Sequence(_) | Value::Mapping(_) => {
+            bail!("sequences and mappings cannot be used as table keys")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use memofs::{InMemoryFs, VfsSnapshot};
+
+    #[test]
+    fn instance_from_vfs() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot(
+            "/foo.yaml",
+            VfsSnapshot::file(
+                r#"
+                  array: [1, 2, 3]
+                  object:
+                    hello: world
+                  "true": true
+                  "false": false
+                  "null": null
+                  int: 1234
+                  float: 1234.5452
+                  1invalidident: nice
+                  10: numeric key
+                "#,
+            ),
+        )
+        .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let instance_snapshot = snapshot_yaml(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.yaml"),
+            "foo",
+        )
+        .unwrap()
+        .unwrap();
+
+        insta::assert_yaml_snapshot!(instance_snapshot);
+    }
+
+    #[test]
+    fn invalid_key() {
+        let mut imfs = InMemoryFs::new();
+        imfs.load_snapshot("/foo.yaml", VfsSnapshot::file("~: value"))
+            .unwrap();
+
+        let vfs = Vfs::new(imfs.clone());
+
+        let result = snapshot_yaml(
+            &InstanceContext::default(),
+            &vfs,
+            Path::new("/foo.yaml"),
+            "foo",
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -13,7 +13,8 @@ use serde::{Deserialize, Serialize};
 use crate::{
     session_id::SessionId,
     snapshot::{
-        AppliedPatchSet, InstanceMetadata as RojoInstanceMetadata, InstanceWithMeta, RojoTree,
+        AppliedPatchSet, IgnoreUnknownInstances, InstanceMetadata as RojoInstanceMetadata,
+        InstanceWithMeta, RojoTree,
     },
 };
 
@@ -21,7 +22,7 @@ use crate::{
 pub(crate) const SERVER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Current protocol version, which is required to match.
-pub const PROTOCOL_VERSION: u64 = 4;
+pub const PROTOCOL_VERSION: u64 = 5;
 
 /// Message returned by Rojo API when a change has occurred.
 #[derive(Debug, Serialize, Deserialize)]
@@ -96,13 +97,13 @@ pub struct InstanceUpdate {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InstanceMetadata {
-    pub ignore_unknown_instances: bool,
+    pub ignore_unknown_instances: IgnoreUnknownInstances,
 }
 
 impl InstanceMetadata {
     pub(crate) fn from_rojo_metadata(meta: &RojoInstanceMetadata) -> Self {
         Self {
-            ignore_unknown_instances: meta.ignore_unknown_instances,
+            ignore_unknown_instances: meta.ignore_unknown_instances.clone(),
         }
     }
 }
@@ -22,13 +22,20 @@ macro_rules! gen_build_tests {
 
 gen_build_tests! {
     init_csv_with_children,
+    init_file_rules,
     attributes,
+    build_profiles,
+    asset_manifest,
     client_in_folder,
     client_init,
+    class_defaults,
     csv_bug_145,
     csv_bug_147,
     csv_in_folder,
+    data_modules,
     deep_nesting,
+    escaped_file_names,
+    escaped_file_names_off,
     gitkeep,
     ignore_glob_inner,
     ignore_glob_nested,
@@ -40,34 +47,140 @@ gen_build_tests! {
     init_with_children,
     issue_546,
     json_as_lua,
+    localization_formats,
     json_model_in_folder,
     json_model_legacy_name,
+    legacy_scripts_meta,
     module_in_folder,
     module_init,
     nested_runcontext,
     optional,
+    place_select,
+    path_glob,
     project_composed_default,
     project_composed_file,
     project_root_name,
+    project_templates,
+    property_files,
     rbxm_in_folder,
     rbxmx_in_folder,
     rbxmx_ref,
+    rbxmx_multiple_roots,
+    reflection_database,
+    ref_paths,
+    strict,
     script_meta_disabled,
     server_in_folder,
     server_init,
     txt,
     txt_in_folder,
     unresolved_values,
+    value_shorthands,
     weldconstraint,
+    yaml_in_folder,
+    yaml_toml_models,
     sync_rule_alone,
     sync_rule_complex,
+    sync_rule_class,
     sync_rule_nested_projects,
     no_name_default_project,
     no_name_project,
     no_name_top_level_project,
 }
 
+#[test]
+fn build_build_profiles_release() {
+    let _ = env_logger::try_init();
+
+    run_build_test_with_args(
+        "build_profiles",
+        "build_profiles_release",
+        &["--profile", "release"],
+    );
+}
+
+#[test]
+fn build_command_middleware() {
+    let _ = env_logger::try_init();
+
+    run_build_test_with_args(
+        "command_middleware",
+        "command_middleware",
+        &["--allow-commands"],
+    );
+}
+
+#[test]
+fn build_command_middleware_not_allowed() {
+    let _ = env_logger::try_init();
+
+    let stderr = run_failing_build_test("command_middleware", &[]);
+    assert!(stderr.contains("--allow-commands"), "{}", stderr);
+}
+
+#[test]
+fn build_ref_path_missing() {
+    let _ = env_logger::try_init();
+
+    let stderr = run_failing_build_test("ref_path_missing", &[]);
+    assert!(
+        stderr.contains("Could not set Ref property Value of"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn build_duplicate_ids() {
+    let _ = env_logger::try_init();
+
+    let stderr = run_failing_build_test("duplicate_ids", &[]);
+    assert!(
+        stderr.contains("Duplicate ID 'target' was given to 2 instances"),
+        "{}",
+        stderr
+    );
+}
+
+/// Builds the project named `test_name`, expecting the build to fail without
+/// writing any output. Returns what Rojo wrote to stderr.
+fn run_failing_build_test(test_name: &str, extra_args: &[&str]) -> String {
+    let input_path = Path::new(BUILD_TESTS_PATH).join(test_name);
+
+    let output_dir = tempdir().expect("couldn't create temporary directory");
+    let output_path = output_dir.path().join(format!("{}.rbxmx", test_name));
+
+    let output = Command::new(ROJO_PATH)
+        .args([
+            "build",
+            input_path.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ])
+        .args(extra_args)
+        .current_dir(get_working_dir_path())
+        .output()
+        .expect("Couldn't start Rojo");
+
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+
+    assert!(
+        !output.status.success(),
+        "Rojo should have failed: {}",
+        stderr
+    );
+    assert!(!output_path.exists());
+
+    stderr
+}
+
 fn run_build_test(test_name: &str) {
+    run_build_test_with_args(test_name, test_name, &[]);
+}
+
+/// Builds the project named `test_name` with the given extra arguments,
+/// comparing the result against the snapshot named `snapshot_name`.
+fn run_build_test_with_args(test_name: &str, snapshot_name: &str, extra_args: &[&str]) {
     let working_dir = get_working_dir_path();
 
     let input_path = Path::new(BUILD_TESTS_PATH).join(test_name);
@@ -82,6 +195,7 @@ fn run_build_test(test_name: &str) {
             "-o",
             output_path.to_str().unwrap(),
         ])
+        .args(extra_args)
         .env("RUST_LOG", "error")
         .current_dir(working_dir)
         .output()
@@ -104,6 +218,6 @@ fn run_build_test(test_name: &str) {
     settings.set_snapshot_path(snapshot_path);
 
     settings.bind(|| {
-        assert_snapshot!(test_name, contents);
+        assert_snapshot!(snapshot_name, contents);
     });
 }
@@ -516,3 +516,227 @@ fn ref_properties_patch_update() {
         );
     });
 }
+
+#[test]
+fn path_glob() {
+    run_serve_test("path_glob", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("path_glob_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "path_glob_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        // Create a new package elsewhere so that it can be moved in as one
+        // event.
+        let src_dir = tempdir().unwrap();
+        let package_path = src_dir.path().join("gamma");
+
+        fs::create_dir_all(package_path.join("src")).unwrap();
+        fs::write(package_path.join("src/Gamma.luau"), "-- Hello, from gamma!").unwrap();
+
+        fs::rename(package_path, session.path().join("packages/gamma")).unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "path_glob_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "path_glob_all-2",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        // Changing the contents of an existing match snapshots it again, and
+        // it should keep the name of its package folder.
+        fs::write(
+            session.path().join("packages/beta/src/New.luau"),
+            "-- Hello, from new!",
+        )
+        .unwrap();
+
+        let subscribe_response = session.get_api_subscribe(1).unwrap();
+        assert_yaml_snapshot!(
+            "path_glob_subscribe-2",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "path_glob_all-3",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+    });
+}
+
+#[test]
+fn property_files() {
+    run_serve_test("property_files", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("property_files_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "property_files_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        fs::write(
+            session.path().join("content/greeting.txt"),
+            "Hello, from a changed file!",
+        )
+        .unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "property_files_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "property_files_all-2",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+    });
+}
+
+#[test]
+fn ref_paths() {
+    run_serve_test("ref_paths", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("ref_paths_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "ref_paths_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        fs::write(
+            session.path().join("src/Pointer.model.json"),
+            r#"{
+                "className": "ObjectValue",
+                "properties": {
+                    "Value": { "Ref": "../../Targets/B" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "ref_paths_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "ref_paths_all-2",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+    });
+}
+
+#[test]
+fn ignore_unknown_patterns() {
+    run_serve_test("ignore_unknown_patterns", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!(
+            "ignore_unknown_patterns_info",
+            redactions.redacted_yaml(info)
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "ignore_unknown_patterns_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        fs::write(
+            session.path().join("src/Map/init.meta.json"),
+            r#"{ "ignoreUnknownInstances": ["class:BasePart", "Spawn"] }"#,
+        )
+        .unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "ignore_unknown_patterns_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+    });
+}
+
+#[test]
+fn legacy_scripts_meta() {
+    run_serve_test("legacy_scripts_meta", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("legacy_scripts_meta_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "legacy_scripts_meta_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        fs::write(session.path().join("src/Migrated/init.meta.json"), "{}").unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "legacy_scripts_meta_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "legacy_scripts_meta_all-2",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+    });
+}
+
+#[test]
+fn place_select() {
+    run_serve_test("place_select", |session, mut redactions| {
+        let info = session.get_api_rojo().unwrap();
+        let root_id = info.root_instance_id;
+
+        assert_yaml_snapshot!("place_select_info", redactions.redacted_yaml(info));
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "place_select_all",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+
+        let map_path = session.path().join("map.rbxlx");
+        let map = fs::read_to_string(&map_path).unwrap();
+        fs::write(&map_path, map.replace("Baseplate", "Floor")).unwrap();
+
+        let subscribe_response = session.get_api_subscribe(0).unwrap();
+        assert_yaml_snapshot!(
+            "place_select_subscribe",
+            subscribe_response.intern_and_redact(&mut redactions, ())
+        );
+
+        let read_response = session.get_api_read(root_id).unwrap();
+        assert_yaml_snapshot!(
+            "place_select_all-2",
+            read_response.intern_and_redact(&mut redactions, root_id)
+        );
+    });
+}